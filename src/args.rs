@@ -0,0 +1,297 @@
+use crate::{Player, MAX_NUMBER_OF_BLOCKS};
+
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Args {
+    pub(crate) seed: Option<u64>,
+    pub(crate) first_turn: Option<Player>,
+    pub(crate) max_blocks: Option<u8>,
+    // file to read both hands from instead of dealing them
+    pub(crate) hands: Option<String>,
+    // file to write every game event to as a line of json
+    pub(crate) events_json: Option<String>,
+    // file to keep a readable transcript of the game log in
+    pub(crate) transcript: Option<String>,
+    // print game events as lines of json instead of drawing the game
+    pub(crate) headless: bool,
+    // file to read moves from instead of stdin, one per line
+    pub(crate) replay: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Error {
+    InvalidMaxBlocks { value: String },
+    InvalidPlayer { value: String },
+    InvalidSeed { value: String },
+    MissingValue { flag: String },
+    UnexpectedArgument { arg: String },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidMaxBlocks { value } => {
+                write!(
+                    f,
+                    "Invalid Max Blocks {value:?}, expected a number from 0 to {MAX_NUMBER_OF_BLOCKS}"
+                )
+            }
+            Error::InvalidPlayer { value } => {
+                write!(f, "Invalid Player {value:?}, expected blue or red")
+            }
+            Error::InvalidSeed { value } => {
+                write!(f, "Invalid Seed {value:?}, expected a positive number")
+            }
+            Error::MissingValue { flag } => write!(f, "Missing value for {flag}"),
+            Error::UnexpectedArgument { arg } => write!(f, "Unexpected Argument {arg:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub(crate) fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, Error> {
+    let mut parsed = Args::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                let value = args.next().ok_or(Error::MissingValue { flag: arg })?;
+                parsed.seed = Some(parse_seed(value)?);
+            }
+            "--first" => {
+                let value = args.next().ok_or(Error::MissingValue { flag: arg })?;
+                parsed.first_turn = Some(parse_player(value)?);
+            }
+            "--max-blocks" => {
+                let value = args.next().ok_or(Error::MissingValue { flag: arg })?;
+                parsed.max_blocks = Some(parse_max_blocks(value)?);
+            }
+            "--hands" => {
+                let value = args.next().ok_or(Error::MissingValue { flag: arg })?;
+                parsed.hands = Some(value);
+            }
+            "--events-json" => {
+                let value = args.next().ok_or(Error::MissingValue { flag: arg })?;
+                parsed.events_json = Some(value);
+            }
+            "--transcript" => {
+                let value = args.next().ok_or(Error::MissingValue { flag: arg })?;
+                parsed.transcript = Some(value);
+            }
+            "--headless" => parsed.headless = true,
+            "--replay" => {
+                let value = args.next().ok_or(Error::MissingValue { flag: arg })?;
+                parsed.replay = Some(value);
+            }
+            _ => return Err(Error::UnexpectedArgument { arg }),
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn parse_seed(value: String) -> Result<u64, Error> {
+    value.parse().map_err(|_| Error::InvalidSeed { value })
+}
+
+fn parse_max_blocks(value: String) -> Result<u8, Error> {
+    match value.parse() {
+        Ok(max_blocks) if max_blocks <= MAX_NUMBER_OF_BLOCKS => Ok(max_blocks),
+        _ => Err(Error::InvalidMaxBlocks { value }),
+    }
+}
+
+fn parse_player(value: String) -> Result<Player, Error> {
+    value.parse().map_err(|_| Error::InvalidPlayer { value })
+}
+
+#[cfg(test)]
+mod test_parse {
+    use super::*;
+
+    fn parse_str(args: &[&str]) -> Result<Args, Error> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parse_no_arguments() {
+        let res = parse_str(&[]);
+        assert_eq!(res, Ok(Args::default()));
+    }
+
+    #[test]
+    fn parse_first_player() {
+        let res = parse_str(&["--first", "blue"]);
+        assert_eq!(
+            res,
+            Ok(Args {
+                first_turn: Some(Player::P1),
+                ..Args::default()
+            })
+        );
+
+        let res = parse_str(&["--first", "RED"]);
+        assert_eq!(
+            res,
+            Ok(Args {
+                first_turn: Some(Player::P2),
+                ..Args::default()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_seed_and_first_player_together() {
+        let res = parse_str(&["--seed", "1234", "--first", "red"]);
+        assert_eq!(
+            res,
+            Ok(Args {
+                seed: Some(1234),
+                first_turn: Some(Player::P2),
+                ..Args::default()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_hands_path() {
+        let res = parse_str(&["--hands", "hands.txt"]);
+        assert_eq!(
+            res,
+            Ok(Args {
+                hands: Some("hands.txt".into()),
+                ..Args::default()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_events_json_path() {
+        let res = parse_str(&["--events-json", "events.jsonl"]);
+        assert_eq!(
+            res,
+            Ok(Args {
+                events_json: Some("events.jsonl".into()),
+                ..Args::default()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_transcript_path() {
+        let res = parse_str(&["--transcript", "game.txt"]);
+        assert_eq!(
+            res,
+            Ok(Args {
+                transcript: Some("game.txt".into()),
+                ..Args::default()
+            })
+        );
+    }
+
+    #[test]
+    fn error_when_events_json_path_is_missing() {
+        let res = parse_str(&["--events-json"]);
+        assert_eq!(
+            res,
+            Err(Error::MissingValue {
+                flag: "--events-json".into()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_replay_path() {
+        let res = parse_str(&["--seed", "7", "--replay", "moves.txt"]);
+        assert_eq!(
+            res,
+            Ok(Args {
+                seed: Some(7),
+                replay: Some("moves.txt".into()),
+                ..Args::default()
+            })
+        );
+    }
+
+    #[test]
+    fn parse_max_blocks_up_to_the_limit() {
+        let res = parse_str(&["--max-blocks", "0"]);
+        assert_eq!(
+            res,
+            Ok(Args {
+                max_blocks: Some(0),
+                ..Args::default()
+            })
+        );
+
+        let res = parse_str(&["--max-blocks", "12"]);
+        assert_eq!(
+            res,
+            Ok(Args {
+                max_blocks: Some(12),
+                ..Args::default()
+            })
+        );
+    }
+
+    #[test]
+    fn error_when_max_blocks_is_over_the_limit() {
+        let res = parse_str(&["--max-blocks", "13"]);
+        assert_eq!(res, Err(Error::InvalidMaxBlocks { value: "13".into() }));
+
+        let res = parse_str(&["--max-blocks", "-1"]);
+        assert_eq!(res, Err(Error::InvalidMaxBlocks { value: "-1".into() }));
+    }
+
+    #[test]
+    fn parse_headless_flag() {
+        let res = parse_str(&["--headless", "--first", "red"]);
+        assert_eq!(
+            res,
+            Ok(Args {
+                headless: true,
+                first_turn: Some(Player::P2),
+                ..Args::default()
+            })
+        );
+    }
+
+    #[test]
+    fn error_when_seed_is_invalid() {
+        let res = parse_str(&["--seed", "-1"]);
+        assert_eq!(res, Err(Error::InvalidSeed { value: "-1".into() }));
+    }
+
+    #[test]
+    fn error_when_first_player_is_invalid() {
+        let res = parse_str(&["--first", "green"]);
+        assert_eq!(
+            res,
+            Err(Error::InvalidPlayer {
+                value: "green".into()
+            })
+        );
+    }
+
+    #[test]
+    fn error_when_first_player_is_missing() {
+        let res = parse_str(&["--first"]);
+        assert_eq!(
+            res,
+            Err(Error::MissingValue {
+                flag: "--first".into()
+            })
+        );
+    }
+
+    #[test]
+    fn error_on_unexpected_argument() {
+        let res = parse_str(&["--second"]);
+        assert_eq!(
+            res,
+            Err(Error::UnexpectedArgument {
+                arg: "--second".into()
+            })
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{
+    stat_digit, Arrows, BattleStat, BattleWinner, Cell, Entry, FlipKind, GameLog, GameState,
+    GameStatus, OwnedCard, Player, BOARD_SIZE,
+};
+use std::fmt::Write;
+
+type Result<T = ()> = std::result::Result<T, std::fmt::Error>;
+
+// writes a line for every log entry after the first `logged` entries, followed by a line for the
+// battle choices while waiting for one or the final result once the game is over, returns the
+// number of entries written so far
+// note: the first update also writes a line describing how the game was set up
+pub(crate) fn update(
+    o: &mut String,
+    log: &GameLog,
+    logged: usize,
+    state: &GameState,
+) -> Result<usize> {
+    let len = log.iter().count();
+
+    // the log always starts with an entry, so nothing has been written only on the first update
+    if logged == 0 {
+        push_setup(o, state)?;
+        writeln!(o)?;
+    }
+
+    // entries are only ever removed when a placement is taken back or a move is undone
+    if len < logged {
+        let entries = logged - len;
+        writeln!(o, r#"{{"type":"take_back","entries":{entries}}}"#)?;
+    }
+
+    for entry in log.iter().skip(logged) {
+        push_entry(o, entry)?;
+        writeln!(o)?;
+    }
+
+    match &state.status {
+        GameStatus::WaitingPlace => {}
+        GameStatus::WaitingBattle {
+            attacker_cell,
+            choices,
+            ..
+        } => {
+            write!(
+                o,
+                r#"{{"type":"waiting_battle","attacker_cell":{attacker_cell}"#
+            )?;
+            write!(o, r#","choices":["#)?;
+            for (idx, &(cell, card)) in choices.iter().enumerate() {
+                let sep = if idx == 0 { "" } else { "," };
+                let card = OwnedCard {
+                    owner: state.turn.opposite(),
+                    card,
+                };
+                write!(o, r#"{sep}{{"cell":{cell},"card":"#)?;
+                push_card(o, &card)?;
+                write!(o, "}}")?;
+            }
+            writeln!(o, "]}}")?;
+        }
+        GameStatus::GameOver {
+            winner,
+            p1_cards,
+            p2_cards,
+        } => {
+            write!(o, r#"{{"type":"game_over","winner":"#)?;
+            match winner {
+                Some(winner) => write!(o, r#""{}""#, winner.name())?,
+                None => write!(o, "null")?,
+            }
+            writeln!(o, r#","p1_cards":{p1_cards},"p2_cards":{p2_cards}}}"#)?;
+        }
+    }
+
+    Ok(len)
+}
+
+pub(crate) fn error(o: &mut String, message: &str) -> Result {
+    write!(o, r#"{{"type":"error","message":"#)?;
+    push_string(o, message)?;
+    writeln!(o, "}}")
+}
+
+fn push_setup(o: &mut String, state: &GameState) -> Result {
+    let first = state.turn.name();
+    write!(
+        o,
+        r#"{{"type":"setup","seed":{},"first":"{first}""#,
+        state.seed
+    )?;
+    write!(o, r#","blocked":["#)?;
+    let blocked = (0..BOARD_SIZE).filter(|&cell| state.board[cell] == Cell::Blocked);
+    for (idx, cell) in blocked.enumerate() {
+        let sep = if idx == 0 { "" } else { "," };
+        write!(o, "{sep}{cell}")?;
+    }
+    write!(o, "]")?;
+    for (key, owner, hand) in [
+        ("p1_hand", Player::P1, &state.p1_hand),
+        ("p2_hand", Player::P2, &state.p2_hand),
+    ] {
+        write!(o, r#","{key}":["#)?;
+        for (idx, card) in hand.iter().enumerate() {
+            let sep = if idx == 0 { "" } else { "," };
+            write!(o, "{sep}")?;
+            match card {
+                Some(card) => push_card(o, &OwnedCard { owner, card: *card })?,
+                None => write!(o, "null")?,
+            }
+        }
+        write!(o, "]")?;
+    }
+    write!(o, "}}")
+}
+
+fn push_entry(o: &mut String, entry: &Entry) -> Result {
+    match entry {
+        Entry::NextTurn { turn } => {
+            write!(o, r#"{{"type":"next_turn","turn":"{}"}}"#, turn.name())
+        }
+        Entry::PlaceCard { card, cell } => {
+            write!(o, r#"{{"type":"place_card","cell":{cell},"card":"#)?;
+            push_card(o, card)?;
+            write!(o, "}}")
+        }
+        Entry::FlipCard {
+            card,
+            cell,
+            to,
+            kind,
+        } => {
+            let kind = match kind {
+                FlipKind::Free => "free",
+                FlipKind::Battle => "battle",
+                FlipKind::Combo => "combo",
+            };
+            write!(o, r#"{{"type":"flip_card","cell":{cell},"card":"#)?;
+            push_card(o, card)?;
+            write!(o, r#","to":"{}","kind":"{kind}"}}"#, to.name())
+        }
+        Entry::Battle {
+            attacker,
+            defender,
+            result,
+        } => {
+            let winner = match result.winner {
+                BattleWinner::Attacker => "attacker",
+                BattleWinner::Defender => "defender",
+                BattleWinner::None => "none",
+            };
+            write!(o, r#"{{"type":"battle","attacker":"#)?;
+            push_card(o, attacker)?;
+            write!(o, r#","defender":"#)?;
+            push_card(o, defender)?;
+            write!(o, r#","attack_stat":"#)?;
+            push_battle_stat(o, result.attack_stat)?;
+            write!(o, r#","defense_stat":"#)?;
+            push_battle_stat(o, result.defense_stat)?;
+            write!(o, r#","winner":"{winner}"}}"#)
+        }
+    }
+}
+
+fn push_card(o: &mut String, owned: &OwnedCard) -> Result {
+    let card = owned.card;
+    let typ = card.card_type.letter();
+    let att = stat_digit(card.attack);
+    let phy = stat_digit(card.physical_defense);
+    let mag = stat_digit(card.magical_defense);
+
+    write!(o, r#"{{"owner":"{}","#, owned.owner.name())?;
+    write!(o, r#""stats":"{att:X}{typ}{phy:X}{mag:X}","arrows":["#)?;
+    for (idx, arrow) in card.arrows.iter().enumerate() {
+        let sep = if idx == 0 { "" } else { "," };
+        let direction = match arrow {
+            Arrows::UP => "up",
+            Arrows::UP_RIGHT => "up_right",
+            Arrows::RIGHT => "right",
+            Arrows::DOWN_RIGHT => "down_right",
+            Arrows::DOWN => "down",
+            Arrows::DOWN_LEFT => "down_left",
+            Arrows::LEFT => "left",
+            Arrows::UP_LEFT => "up_left",
+            _ => unreachable!("Arrows::iter only yields single arrows"),
+        };
+        write!(o, r#"{sep}"{direction}""#)?;
+    }
+    write!(o, "]}}")
+}
+
+fn push_battle_stat(o: &mut String, stat: BattleStat) -> Result {
+    let BattleStat { digit, value, roll } = stat;
+    write!(o, r#"{{"digit":{digit},"value":{value},"roll":{roll}}}"#)
+}
+
+fn push_string(o: &mut String, value: &str) -> Result {
+    o.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => o.push_str(r#"\""#),
+            '\\' => o.push_str(r"\\"),
+            ch if ch.is_control() => write!(o, r"\u{:04x}", ch as u32)?,
+            ch => o.push(ch),
+        }
+    }
+    o.push('"');
+    Ok(())
+}
@@ -1,7 +1,8 @@
-use crate::{BattleResult, OwnedCard, Player};
+use crate::{Arrows, BattleResult, BattleStat, BattleWinner, Card, CardType, OwnedCard, Player};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum Entry {
+    // not logged after the final move, the game over status takes its place
     NextTurn {
         turn: Player,
     },
@@ -13,7 +14,7 @@ pub(crate) enum Entry {
         card: OwnedCard,
         cell: usize,
         to: Player,
-        via_combo: bool,
+        kind: FlipKind,
     },
     Battle {
         attacker: OwnedCard,
@@ -31,12 +32,12 @@ impl Entry {
         Entry::PlaceCard { card, cell }
     }
 
-    pub(crate) fn flip_card(card: OwnedCard, cell: usize, to: Player, via_combo: bool) -> Self {
+    pub(crate) fn flip_card(card: OwnedCard, cell: usize, to: Player, kind: FlipKind) -> Self {
         Entry::FlipCard {
             card,
             cell,
             to,
-            via_combo,
+            kind,
         }
     }
 
@@ -47,8 +48,35 @@ impl Entry {
             result,
         }
     }
+
+    // returns the change in the number of cards on the board owned by (P1, P2)
+    pub(crate) fn score_delta(&self) -> (i8, i8) {
+        match self {
+            Entry::PlaceCard { card, .. } => match card.owner {
+                Player::P1 => (1, 0),
+                Player::P2 => (0, 1),
+            },
+            Entry::FlipCard { to, .. } => match to {
+                Player::P1 => (1, -1),
+                Player::P2 => (-1, 1),
+            },
+            Entry::NextTurn { .. } | Entry::Battle { .. } => (0, 0),
+        }
+    }
 }
 
+// how a card came to be flipped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FlipKind {
+    // pointed to by the attacker without pointing back
+    Free,
+    // lost a battle
+    Battle,
+    // pointed to by a card that lost a battle
+    Combo,
+}
+
+#[derive(Clone)]
 pub(crate) struct GameLog {
     entries: Vec<Entry>,
 }
@@ -63,7 +91,354 @@ impl GameLog {
         self.entries.push(entry);
     }
 
+    pub(crate) fn pop(&mut self) -> Option<Entry> {
+        self.entries.pop()
+    }
+
     pub(crate) fn iter(&self) -> std::slice::Iter<'_, Entry> {
         self.entries.iter()
     }
+
+    // returns the number of cards on the board owned by (P1, P2) at the end of every turn,
+    // the last one being the turn that is still being played
+    pub(crate) fn score_timeline(&self) -> Vec<(u8, u8)> {
+        let mut timeline = vec![];
+        let mut score = (0u8, 0u8);
+        for (idx, entry) in self.entries.iter().enumerate() {
+            if idx > 0 && matches!(entry, Entry::NextTurn { .. }) {
+                timeline.push(score);
+            }
+
+            let (p1, p2) = entry.score_delta();
+            score = (
+                score.0.saturating_add_signed(p1),
+                score.1.saturating_add_signed(p2),
+            );
+        }
+        timeline.push(score);
+        timeline
+    }
+}
+
+// the transcript has one line per entry, made up of fields separated by spaces
+//
+//   next_turn {player}
+//   place_card {cell} {card}
+//   flip_card {cell} {card} {to player} {kind}
+//   battle {attacker card} {defender card} {winner} {attack stat} {defense stat}
+//
+// players are blue or red, kinds are free, battle or combo and winners are attacker, defender
+// or none
+// cells are a hex digit, cards are {owner} {type} {attack} {physical defense} {magical defense}
+// {arrows} with the type as P, M, X or A and the rest in hex, battle stats are {digit} {value}
+// {roll} in hex
+// note: the log has no game over entry, the result follows from the final flips
+impl std::fmt::Display for GameLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            match entry {
+                Entry::NextTurn { turn } => write!(f, "next_turn {}", turn.name())?,
+                Entry::PlaceCard { card, cell } => {
+                    write!(f, "place_card {cell:X} ")?;
+                    write_card(f, card)?;
+                }
+                Entry::FlipCard {
+                    card,
+                    cell,
+                    to,
+                    kind,
+                } => {
+                    let kind = match kind {
+                        FlipKind::Free => "free",
+                        FlipKind::Battle => "battle",
+                        FlipKind::Combo => "combo",
+                    };
+                    write!(f, "flip_card {cell:X} ")?;
+                    write_card(f, card)?;
+                    write!(f, " {} {kind}", to.name())?;
+                }
+                Entry::Battle {
+                    attacker,
+                    defender,
+                    result,
+                } => {
+                    let winner = match result.winner {
+                        BattleWinner::Attacker => "attacker",
+                        BattleWinner::Defender => "defender",
+                        BattleWinner::None => "none",
+                    };
+                    write!(f, "battle ")?;
+                    write_card(f, attacker)?;
+                    write!(f, " ")?;
+                    write_card(f, defender)?;
+                    write!(f, " {winner} ")?;
+                    write_battle_stat(f, result.attack_stat)?;
+                    write!(f, " ")?;
+                    write_battle_stat(f, result.defense_stat)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_card(f: &mut std::fmt::Formatter<'_>, owned: &OwnedCard) -> std::fmt::Result {
+    let card = owned.card;
+    let typ = card.card_type.letter();
+    let (att, phy, mag) = (card.attack, card.physical_defense, card.magical_defense);
+    let owner = owned.owner.name();
+    let arrows = card.arrows.0;
+    write!(f, "{owner} {typ} {att:X} {phy:X} {mag:X} {arrows:X}")
+}
+
+fn write_battle_stat(f: &mut std::fmt::Formatter<'_>, stat: BattleStat) -> std::fmt::Result {
+    let BattleStat { digit, value, roll } = stat;
+    write!(f, "{digit:X} {value:X} {roll:X}")
+}
+
+// reads back a transcript in the format written by the Display impl
+impl std::str::FromStr for GameLog {
+    type Err = ParseTranscriptError;
+
+    fn from_str(transcript: &str) -> Result<Self, Self::Err> {
+        let mut entries = vec![];
+        for (idx, line) in transcript.lines().enumerate() {
+            let mut fields = line.split(' ');
+            let entry = parse_entry(&mut fields).filter(|_| fields.next().is_none());
+            entries.push(entry.ok_or(ParseTranscriptError { line: idx + 1 })?);
+        }
+        Ok(GameLog { entries })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct ParseTranscriptError {
+    // the first line that couldn't be parsed, starting from 1
+    pub(crate) line: usize,
+}
+
+fn parse_entry<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<Entry> {
+    Some(match fields.next()? {
+        "next_turn" => Entry::next_turn(parse_player(fields)?),
+        "place_card" => {
+            let cell = parse_cell(fields)?;
+            Entry::place_card(parse_card(fields)?, cell)
+        }
+        "flip_card" => {
+            let cell = parse_cell(fields)?;
+            let card = parse_card(fields)?;
+            let to = parse_player(fields)?;
+            let kind = match fields.next()? {
+                "free" => FlipKind::Free,
+                "battle" => FlipKind::Battle,
+                "combo" => FlipKind::Combo,
+                _ => return None,
+            };
+            Entry::flip_card(card, cell, to, kind)
+        }
+        "battle" => {
+            let attacker = parse_card(fields)?;
+            let defender = parse_card(fields)?;
+            let winner = match fields.next()? {
+                "attacker" => BattleWinner::Attacker,
+                "defender" => BattleWinner::Defender,
+                "none" => BattleWinner::None,
+                _ => return None,
+            };
+            let attack_stat = parse_battle_stat(fields)?;
+            let defense_stat = parse_battle_stat(fields)?;
+            let result = BattleResult {
+                winner,
+                attack_stat,
+                defense_stat,
+            };
+            Entry::battle(attacker, defender, result)
+        }
+        _ => return None,
+    })
+}
+
+fn parse_player<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<Player> {
+    match fields.next()? {
+        "blue" => Some(Player::P1),
+        "red" => Some(Player::P2),
+        _ => None,
+    }
+}
+
+fn parse_cell<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<usize> {
+    usize::from_str_radix(fields.next()?, 16)
+        .ok()
+        .filter(|&cell| cell < crate::BOARD_SIZE)
+}
+
+fn parse_hex<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<u8> {
+    u8::from_str_radix(fields.next()?, 16).ok()
+}
+
+fn parse_card<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<OwnedCard> {
+    let owner = parse_player(fields)?;
+    let card_type = match fields.next()? {
+        "P" => CardType::Physical,
+        "M" => CardType::Magical,
+        "X" => CardType::Exploit,
+        "A" => CardType::Assault,
+        _ => return None,
+    };
+    let card = Card {
+        card_type,
+        attack: parse_hex(fields)?,
+        physical_defense: parse_hex(fields)?,
+        magical_defense: parse_hex(fields)?,
+        arrows: Arrows(parse_hex(fields)?),
+    };
+    Some(OwnedCard { owner, card })
+}
+
+fn parse_battle_stat<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<BattleStat> {
+    Some(BattleStat {
+        digit: parse_hex(fields)?,
+        value: parse_hex(fields)?,
+        roll: parse_hex(fields)?,
+    })
+}
+
+#[cfg(test)]
+mod test_transcript {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const TRANSCRIPT: &str = "\
+next_turn blue
+place_card 5 blue P FF F 1F 88
+battle blue P FF F 1F 88 red M 0 7F 2F 8 attacker 0 FF 8E 2 F F
+flip_card 1 red M 0 7F 2F 8 blue battle
+flip_card 0 red X 10 20 30 0 blue combo
+flip_card 9 red A A0 B0 C0 1 blue free
+next_turn red
+";
+
+    #[test]
+    fn parse_and_write_a_transcript() {
+        let log: GameLog = TRANSCRIPT.parse().unwrap();
+
+        let card = |owner, card: &str| OwnedCard {
+            owner,
+            card: Card::try_parse(card).unwrap(),
+        };
+        let attacker = card(Player::P1, "FP01_88");
+        let defender = OwnedCard {
+            owner: Player::P2,
+            card: Card {
+                card_type: CardType::Magical,
+                attack: 0x00,
+                physical_defense: 0x7F,
+                magical_defense: 0x2F,
+                arrows: Arrows::DOWN,
+            },
+        };
+        let comboed = OwnedCard {
+            owner: Player::P2,
+            card: Card {
+                card_type: CardType::Exploit,
+                attack: 0x10,
+                physical_defense: 0x20,
+                magical_defense: 0x30,
+                arrows: Arrows::NONE,
+            },
+        };
+        let flipped = OwnedCard {
+            owner: Player::P2,
+            card: Card {
+                card_type: CardType::Assault,
+                attack: 0xA0,
+                physical_defense: 0xB0,
+                magical_defense: 0xC0,
+                arrows: Arrows::UP_LEFT,
+            },
+        };
+        let result = BattleResult {
+            winner: BattleWinner::Attacker,
+            attack_stat: BattleStat {
+                digit: 0,
+                value: 0xFF,
+                roll: 0x8E,
+            },
+            defense_stat: BattleStat {
+                digit: 2,
+                value: 0x0F,
+                roll: 0x0F,
+            },
+        };
+        assert_eq!(
+            log.iter().collect::<Vec<_>>(),
+            vec![
+                &Entry::next_turn(Player::P1),
+                &Entry::place_card(attacker, 5),
+                &Entry::battle(attacker, defender, result),
+                &Entry::flip_card(defender, 1, Player::P1, FlipKind::Battle),
+                &Entry::flip_card(comboed, 0, Player::P1, FlipKind::Combo),
+                &Entry::flip_card(flipped, 9, Player::P1, FlipKind::Free),
+                &Entry::next_turn(Player::P2),
+            ]
+        );
+
+        assert_eq!(log.to_string(), TRANSCRIPT);
+    }
+
+    #[test]
+    fn error_on_the_first_bad_line() {
+        let cases = [
+            ("next_turn blue\nnext_turn green\n", 2),
+            ("place_card 5 blue P FF 0 1F\n", 1),
+            ("next_turn red\nplace_card 10 red P 0 0 0 0\n", 2),
+            ("next_turn red extra\n", 1),
+            ("flip_card 0 red X 10 20 30 0 blue flop\n", 1),
+            ("next_turn blue\n\n", 2),
+            ("skip_turn blue\n", 1),
+        ];
+        for (transcript, line) in cases {
+            let res = transcript.parse::<GameLog>().map(|_| ());
+            assert_eq!(res, Err(ParseTranscriptError { line }), "{transcript:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_score_timeline {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn score_at_the_end_of_every_turn() {
+        let card = |owner, card: &str| OwnedCard {
+            owner,
+            card: Card::try_parse(card).unwrap(),
+        };
+        let blue = card(Player::P1, "0P00_0");
+        let red = card(Player::P2, "0P00_2");
+        let blue_taker = card(Player::P1, "0P00_81");
+
+        let mut log = GameLog::new(Player::P1);
+        assert_eq!(log.score_timeline(), vec![(0, 0)]);
+
+        // blue places a card, red takes it, then blue takes both back
+        log.append(Entry::place_card(blue, 0x0));
+        log.append(Entry::next_turn(Player::P2));
+        log.append(Entry::place_card(red, 0x1));
+        log.append(Entry::flip_card(blue, 0x0, Player::P2, FlipKind::Free));
+        log.append(Entry::next_turn(Player::P1));
+        assert_eq!(log.score_timeline(), vec![(1, 0), (0, 2), (0, 2)]);
+
+        // the last card ends the game without starting another turn
+        log.append(Entry::place_card(blue_taker, 0x5));
+        log.append(Entry::flip_card(red, 0x1, Player::P1, FlipKind::Free));
+        let blue = OwnedCard {
+            owner: Player::P2,
+            ..blue
+        };
+        log.append(Entry::flip_card(blue, 0x0, Player::P1, FlipKind::Free));
+        assert_eq!(log.score_timeline(), vec![(1, 0), (0, 2), (3, 0)]);
+    }
 }
@@ -28,6 +28,7 @@ impl std::fmt::Display for Error {
 pub(crate) fn parse(state: &GameState, input: &str) -> Result<Input, Error> {
     Ok(match &state.status {
         GameStatus::WaitingPlace => Input::Place(parse_place(input)?),
+        GameStatus::WaitingBattle { .. } if is_cancel(input) => Input::Cancel,
         GameStatus::WaitingBattle { .. } => Input::Battle(parse_battle(input)?),
         GameStatus::GameOver { .. } => panic!("parse shouldn't be called once game is over"),
     })
@@ -71,6 +72,14 @@ fn parse_battle(input: &str) -> Result<InputBattle, Error> {
     Ok(InputBattle { cell })
 }
 
+fn is_cancel(input: &str) -> bool {
+    input.trim().eq_ignore_ascii_case("x")
+}
+
+pub(crate) fn is_undo(input: &str) -> bool {
+    input.trim().eq_ignore_ascii_case("u")
+}
+
 fn char_to_card(ch: char) -> Result<usize, Error> {
     Ok(match ch {
         '0' => 0,
@@ -209,3 +218,39 @@ mod test_parse_battle {
         assert_eq!(res, Err(Error::UnexpectedCharacter { ch: '7' }));
     }
 }
+
+#[cfg(test)]
+mod test_is_cancel {
+    use super::*;
+
+    #[test]
+    fn accept_x_in_either_case_surrounded_by_whitespace() {
+        assert!(is_cancel("x"));
+        assert!(is_cancel("  X \n"));
+    }
+
+    #[test]
+    fn reject_anything_else() {
+        assert!(!is_cancel(""));
+        assert!(!is_cancel("x1"));
+        assert!(!is_cancel("cancel"));
+    }
+}
+
+#[cfg(test)]
+mod test_is_undo {
+    use super::*;
+
+    #[test]
+    fn accept_u_in_either_case_surrounded_by_whitespace() {
+        assert!(is_undo("u"));
+        assert!(is_undo("  U \n"));
+    }
+
+    #[test]
+    fn reject_anything_else() {
+        assert!(!is_undo(""));
+        assert!(!is_undo("u1"));
+        assert!(!is_undo("undo"));
+    }
+}
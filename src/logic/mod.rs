@@ -1,7 +1,9 @@
 use crate::{
-    Arrows, BattleResult, BattleStat, BattleWinner, Card, CardType, Cell, Entry, GameLog,
-    GameState, GameStatus, Input, InputBattle, InputPlace, OwnedCard, Player,
+    Arrows, BattleResult, BattleStat, BattleWinner, Card, CardType, Cell, Entry, FlipKind, GameLog,
+    GamePhase, GameState, GameStatus, Input, InputBattle, InputPlace, OwnedCard, Player,
+    BOARD_SIZE, ENDGAME_PLACEMENTS, HAND_SIZE, OPENING_PLACEMENTS,
 };
+use std::collections::VecDeque;
 
 pub(crate) fn next(state: &mut GameState, log: &mut GameLog, input: Input) -> Result<(), String> {
     match (&state.status, input) {
@@ -9,7 +11,55 @@ pub(crate) fn next(state: &mut GameState, log: &mut GameLog, input: Input) -> Re
         (GameStatus::WaitingBattle { .. }, Input::Battle(input)) => {
             handle_waiting_battle(state, log, input)
         }
-        _ => unreachable!("next called with invalid status/input pair"),
+        (GameStatus::WaitingBattle { .. }, Input::Cancel) => handle_cancel(state, log),
+        (GameStatus::WaitingPlace, Input::Battle(_)) => {
+            Err("Expected a card to place, not a battle choice".into())
+        }
+        (GameStatus::WaitingPlace, Input::Cancel) => {
+            Err("There is no placement to take back".into())
+        }
+        (GameStatus::WaitingBattle { .. }, Input::Place(_)) => {
+            Err("Expected a battle choice, not a card to place".into())
+        }
+        (GameStatus::GameOver { .. }, _) => Err("Game is already over".into()),
+    }
+}
+
+// remembers the state from before the last few accepted inputs so moves can be undone one at a
+// time, the oldest one is forgotten once there are more than History::CAPACITY
+#[derive(Default)]
+pub(crate) struct History {
+    // each state along with the log at that point, oldest first
+    // note: the whole log is kept since a cancel removes entries as well as adding them
+    states: VecDeque<(GameState, GameLog)>,
+}
+
+impl History {
+    pub(crate) const CAPACITY: usize = 8;
+
+    // same as next, but keeps the previous state around to undo to
+    pub(crate) fn next(
+        &mut self,
+        state: &mut GameState,
+        log: &mut GameLog,
+        input: Input,
+    ) -> Result<(), String> {
+        let previous = (state.clone(), log.clone());
+        next(state, log, input)?;
+        if self.states.len() == History::CAPACITY {
+            self.states.pop_front();
+        }
+        self.states.push_back(previous);
+        Ok(())
+    }
+
+    // restores the state from before the last accepted input
+    // note: the rng is restored as well, so undoing a battle doesn't reroll it
+    pub(crate) fn undo(&mut self, state: &mut GameState, log: &mut GameLog) -> Result<(), String> {
+        let (previous, previous_log) = self.states.pop_back().ok_or("Nothing to undo")?;
+        *state = previous;
+        *log = previous_log;
+        Ok(())
     }
 }
 
@@ -26,6 +76,14 @@ fn handle_waiting_place(
         Player::P2 => &mut state.p2_hand,
     };
 
+    // ensure a move is possible at all, this should never happen in a regular game
+    if hand.iter().all(Option::is_none) {
+        return Err("No cards left in hand to place".into());
+    }
+    if !state.board.contains(&Cell::Empty) {
+        return Err("No empty cells left to place a card on".into());
+    }
+
     // ensure cell being placed is empty
     if !matches!(state.board[attacker_cell], Cell::Empty) {
         return Err(format!("Cell {:X} is not empty", attacker_cell));
@@ -45,8 +103,34 @@ fn handle_waiting_place(
     // place card onto the board
     log.append(Entry::place_card(attacker, attacker_cell));
     state.board[attacker_cell] = Cell::Card(attacker);
+    state.move_count += 1;
+
+    resolve_interactions(state, log, attacker_cell, Some(hand_index));
+
+    Ok(())
+}
+
+fn handle_cancel(state: &mut GameState, log: &mut GameLog) -> Result<(), String> {
+    let (attacker_cell, hand_index) = match state.status {
+        GameStatus::WaitingBattle {
+            attacker_cell,
+            placed_from: Some(hand_index),
+            ..
+        } => (attacker_cell, hand_index),
+        _ => return Err("Card can't be taken back after a battle has been fought".into()),
+    };
 
-    resolve_interactions(state, log, attacker_cell);
+    // no battles have been fought yet so placing the card is the last thing that happened
+    log.pop();
+    let attacker = state.take_card(attacker_cell);
+    let hand = match state.turn {
+        Player::P1 => &mut state.p1_hand,
+        Player::P2 => &mut state.p2_hand,
+    };
+    hand[hand_index] = Some(attacker.card);
+    state.status = GameStatus::WaitingPlace;
+    // the placement is undone, so it no longer counts as a move
+    state.move_count -= 1;
 
     Ok(())
 }
@@ -62,6 +146,7 @@ fn handle_waiting_battle(
         GameStatus::WaitingBattle {
             attacker_cell,
             choices,
+            ..
         } => (*attacker_cell, choices),
         _ => unreachable!(),
     };
@@ -76,16 +161,21 @@ fn handle_waiting_battle(
     // if the attacker won
     // resolve further interactions
     if winner == BattleWinner::Attacker {
-        resolve_interactions(state, log, attacker_cell);
+        resolve_interactions(state, log, attacker_cell, None);
     } else {
-        check_for_game_over(state);
+        end_turn(state, log);
     }
 
     Ok(())
 }
 
 // common logic for both handle_waiting_place and handle_waiting_battle
-fn resolve_interactions(state: &mut GameState, log: &mut GameLog, attacker_cell: usize) {
+fn resolve_interactions(
+    state: &mut GameState,
+    log: &mut GameLog,
+    attacker_cell: usize,
+    placed_from: Option<usize>,
+) {
     let attacker = match state.board[attacker_cell] {
         Cell::Card(card) => card,
         _ => unreachable!("resolve_interactions can't be called with an invalid attacker_cell"),
@@ -103,7 +193,7 @@ fn resolve_interactions(state: &mut GameState, log: &mut GameLog, attacker_cell:
             continue;
         }
 
-        if defender.card.arrows.has(arrow.reverse()) {
+        if is_standoff(defender.card, arrow) {
             defenders.push((defender_cell, defender.card));
         } else {
             non_defenders.push(defender_cell);
@@ -115,6 +205,7 @@ fn resolve_interactions(state: &mut GameState, log: &mut GameLog, attacker_cell:
         state.status = GameStatus::WaitingBattle {
             attacker_cell,
             choices: defenders,
+            placed_from,
         };
         return;
     }
@@ -126,7 +217,9 @@ fn resolve_interactions(state: &mut GameState, log: &mut GameLog, attacker_cell:
 
     // if the attacker won or if there was no battle
     // handle free flips
-    if winner == Some(BattleWinner::Attacker) || winner == None {
+    // note: free flips never cascade, only a card that loses a battle combos the cards it
+    // points at
+    if winner == Some(BattleWinner::Attacker) || winner.is_none() {
         for cell in non_defenders {
             let defender = match &mut state.board[cell] {
                 Cell::Card(card) => card,
@@ -134,31 +227,60 @@ fn resolve_interactions(state: &mut GameState, log: &mut GameLog, attacker_cell:
             };
             // skip card if it's already been flipped by a battle
             if defender.owner != attacker.owner {
-                flip(log, defender, cell, false);
+                flip(log, defender, cell, FlipKind::Free);
             }
         }
     }
 
-    // next turn
-    state.turn = state.turn.opposite();
-    log.append(Entry::next_turn(state.turn));
+    debug_assert!(
+        log_matches_board(log, &state.board),
+        "the game log doesn't match the board"
+    );
+    end_turn(state, log);
+}
+
+// returns true if every card on the board belongs to whoever the log last gave it to
+// cards the log never mentions, like ones set up before the game, are skipped
+fn log_matches_board(log: &GameLog, board: &[Cell; BOARD_SIZE]) -> bool {
+    let mut owners: [Option<Player>; BOARD_SIZE] = Default::default();
+    for entry in log.iter() {
+        match entry {
+            Entry::PlaceCard { card, cell } => owners[*cell] = Some(card.owner),
+            Entry::FlipCard { cell, to, .. } => owners[*cell] = Some(*to),
+            Entry::NextTurn { .. } | Entry::Battle { .. } => {}
+        }
+    }
 
+    owners
+        .iter()
+        .zip(board)
+        .all(|(owner, cell)| match (owner, cell) {
+            (Some(owner), Cell::Card(card)) => card.owner == *owner,
+            (Some(_), _) => false,
+            (None, _) => true,
+        })
+}
+
+// hands the turn over to the other player
+// note: the game over status replaces the next turn entry on the final move, the
+// log never ends with a next turn entry once the game is over
+fn end_turn(state: &mut GameState, log: &mut GameLog) {
+    state.turn = state.turn.opposite();
     check_for_game_over(state);
+
+    if !matches!(state.status, GameStatus::GameOver { .. }) {
+        state.turn_number += 1;
+        log.append(Entry::next_turn(state.turn));
+    }
 }
 
 fn check_for_game_over(state: &mut GameState) {
-    if state.p1_hand.iter().all(Option::is_none) && state.p2_hand.iter().all(Option::is_none) {
-        let mut p1_cards = 0;
-        let mut p2_cards = 0;
-
-        for cell in &state.board {
-            if let Cell::Card(OwnedCard { owner, .. }) = cell {
-                match owner {
-                    Player::P1 => p1_cards += 1,
-                    Player::P2 => p2_cards += 1,
-                }
-            }
-        }
+    let hands_empty =
+        state.p1_hand.iter().all(Option::is_none) && state.p2_hand.iter().all(Option::is_none);
+    // with enough blocked cells the board can fill up before every card is placed
+    let board_full = !state.board.contains(&Cell::Empty);
+    if hands_empty || board_full {
+        let (p1_cards, p2_cards) = count_cards(&state.board);
 
         use std::cmp::Ordering;
         let winner = match p1_cards.cmp(&p2_cards) {
@@ -167,12 +289,33 @@ fn check_for_game_over(state: &mut GameState) {
             Ordering::Equal => None,
         };
 
-        state.status = GameStatus::GameOver { winner };
+        state.status = GameStatus::GameOver {
+            winner,
+            p1_cards,
+            p2_cards,
+        };
     } else {
         state.status = GameStatus::WaitingPlace;
     }
 }
 
+// returns the number of cards on the board owned by (P1, P2)
+pub(crate) fn count_cards(board: &[Cell; BOARD_SIZE]) -> (u8, u8) {
+    let mut p1_cards = 0u8;
+    let mut p2_cards = 0u8;
+
+    for cell in board {
+        if let Cell::Card(OwnedCard { owner, .. }) = cell {
+            match owner {
+                Player::P1 => p1_cards += 1,
+                Player::P2 => p2_cards += 1,
+            }
+        }
+    }
+
+    (p1_cards, p2_cards)
+}
+
 fn battle(
     state: &mut GameState,
     log: &mut GameLog,
@@ -188,12 +331,12 @@ fn battle(
     let (loser_cell, loser) = match result.winner {
         BattleWinner::Defender | BattleWinner::None => {
             // flip attacker
-            flip(log, &mut attacker, attacker_cell, false);
+            flip(log, &mut attacker, attacker_cell, FlipKind::Battle);
             (attacker_cell, attacker)
         }
         BattleWinner::Attacker => {
             // flip defender
-            flip(log, &mut defender, defender_cell, false);
+            flip(log, &mut defender, defender_cell, FlipKind::Battle);
             (defender_cell, defender)
         }
     };
@@ -209,7 +352,7 @@ fn battle(
             continue;
         }
 
-        flip(log, comboed, comboed_cell, true);
+        flip(log, comboed, comboed_cell, FlipKind::Combo);
     }
 
     // place both cards back into the board
@@ -219,14 +362,35 @@ fn battle(
     result.winner
 }
 
-fn flip(log: &mut GameLog, card: &mut OwnedCard, cell: usize, via_combo: bool) {
+fn flip(log: &mut GameLog, card: &mut OwnedCard, cell: usize, kind: FlipKind) {
     let to = card.owner.opposite();
-    log.append(Entry::flip_card(*card, cell, to, via_combo));
+    log.append(Entry::flip_card(*card, cell, to, kind));
     card.owner = to;
 }
 
+// returns the digits of the stats that would be used if the attacker battled the defender
+// as (attack digit, defense digit)
+pub(crate) fn get_battle_digits(attacker: Card, defender: Card) -> (u8, u8) {
+    let (attack_digit, _) = choose_attack_stat(attacker);
+    let (defense_digit, _) = choose_defense_stat(attacker, defender);
+    (attack_digit, defense_digit)
+}
+
 fn get_attack_stat(rng: &fastrand::Rng, attacker: Card) -> BattleStat {
-    let (digit, value) = if let CardType::Assault = attacker.card_type {
+    let (digit, value) = choose_attack_stat(attacker);
+    let roll = rng.u8(..=value);
+    BattleStat { digit, value, roll }
+}
+
+fn get_defense_stat(rng: &fastrand::Rng, attacker: Card, defender: Card) -> BattleStat {
+    let (digit, value) = choose_defense_stat(attacker, defender);
+    let roll = rng.u8(..=value);
+    BattleStat { digit, value, roll }
+}
+
+// returns the (digit, value) of the stat the attacker uses
+fn choose_attack_stat(attacker: Card) -> (u8, u8) {
+    if let CardType::Assault = attacker.card_type {
         // use the highest stat
         let att = attacker.attack;
         let phy = attacker.physical_defense;
@@ -241,14 +405,12 @@ fn get_attack_stat(rng: &fastrand::Rng, attacker: Card) -> BattleStat {
     } else {
         // otherwise use the attack stat
         (0, attacker.attack)
-    };
-
-    let roll = rng.u8(..=value);
-    BattleStat { digit, value, roll }
+    }
 }
 
-fn get_defense_stat(rng: &fastrand::Rng, attacker: Card, defender: Card) -> BattleStat {
-    let (digit, value) = match attacker.card_type {
+// returns the (digit, value) of the stat the defender uses against the attacker
+fn choose_defense_stat(attacker: Card, defender: Card) -> (u8, u8) {
+    match attacker.card_type {
         CardType::Physical => (2, defender.physical_defense),
         CardType::Magical => (3, defender.magical_defense),
         CardType::Exploit => {
@@ -272,10 +434,31 @@ fn get_defense_stat(rng: &fastrand::Rng, attacker: Card, defender: Card) -> Batt
                 (3, mag)
             }
         }
-    };
+    }
+}
 
-    let roll = rng.u8(..=value);
-    BattleStat { digit, value, roll }
+// returns the chance of each battle outcome as (attacker wins, defender wins, draw)
+// the resolved stats are uniformly distributed between 0 and the stat value so every pair of
+// resolved stats is equally likely
+pub(crate) fn get_battle_odds(attacker: Card, defender: Card) -> (f64, f64, f64) {
+    let (_, att) = choose_attack_stat(attacker);
+    let (_, def) = choose_defense_stat(attacker, defender);
+    let (att, def) = (u32::from(att), u32::from(def));
+
+    let mut wins = 0;
+    let mut draws = 0;
+    for resolved_att in 0..=att {
+        // number of resolved defense stats lower than the resolved attack stat
+        wins += resolved_att.min(def + 1);
+        if resolved_att <= def {
+            draws += 1;
+        }
+    }
+
+    let total = f64::from((att + 1) * (def + 1));
+    let wins = f64::from(wins) / total;
+    let draws = f64::from(draws) / total;
+    (wins, 1.0 - wins - draws, draws)
 }
 
 fn calculate_battle_result(rng: &fastrand::Rng, attacker: Card, defender: Card) -> BattleResult {
@@ -299,6 +482,247 @@ fn calculate_battle_result(rng: &fastrand::Rng, attacker: Card, defender: Card)
     }
 }
 
+// returns the number of placements left until the game is over, that is the cards left in both
+// hands
+pub(crate) fn get_remaining_moves(state: &GameState) -> usize {
+    let in_hand = |hand: &[Option<Card>]| hand.iter().filter(|card| card.is_some()).count();
+    in_hand(&state.p1_hand) + in_hand(&state.p2_hand)
+}
+
+// returns the phase of the game, the endgame takes priority in case both apply
+pub(crate) fn get_phase(state: &GameState) -> GamePhase {
+    if get_remaining_moves(state) <= ENDGAME_PLACEMENTS {
+        GamePhase::Endgame
+    } else if (state.move_count as usize) < OPENING_PLACEMENTS {
+        GamePhase::Opening
+    } else {
+        GamePhase::Midgame
+    }
+}
+
+// returns the card in the player's hand and the cell it should be placed on to swing the score
+// the most in the player's favour as (hand index, cell, number of opponent cards it reaches),
+// ignoring any combos, or None if no placement is expected to gain the player anything
+// a card flipped outright counts fully while a battle only counts by how much more likely it is
+// to be won than lost, since losing it flips the attacker instead
+pub(crate) fn most_threatening_card(
+    state: &GameState,
+    player: Player,
+) -> Option<(usize, usize, usize)> {
+    let hand = match player {
+        Player::P1 => &state.p1_hand,
+        Player::P2 => &state.p2_hand,
+    };
+
+    let mut threat = None;
+    let mut best_swing = 0.0;
+    for (hand_index, card) in hand.iter().enumerate() {
+        let card = match card {
+            Some(card) => OwnedCard {
+                owner: player,
+                card: *card,
+            },
+            None => continue,
+        };
+
+        for cell in 0..BOARD_SIZE {
+            if state.board[cell] != Cell::Empty {
+                continue;
+            }
+
+            let mut count = 0;
+            let mut swing = 0.0;
+            for &(neighbour, arrow) in get_possible_neighbours(cell) {
+                let Cell::Card(defender) = state.board[neighbour] else {
+                    continue;
+                };
+                if !does_interact(card, defender, arrow) {
+                    continue;
+                }
+
+                count += 1;
+                swing += if is_standoff(defender.card, arrow) {
+                    let (win, _, _) = get_battle_odds(card.card, defender.card);
+                    2.0 * win - 1.0
+                } else {
+                    1.0
+                };
+            }
+
+            if swing > best_swing {
+                best_swing = swing;
+                threat = Some((hand_index, cell, count));
+            }
+        }
+    }
+
+    threat
+}
+
+// returns for each card in the player's hand the most opponent cards it could flip or battle
+// from a single placement, ignoring any combos
+pub(crate) fn get_flip_potential(state: &GameState, player: Player) -> [usize; HAND_SIZE] {
+    let hand = match player {
+        Player::P1 => &state.p1_hand,
+        Player::P2 => &state.p2_hand,
+    };
+
+    let mut potential = [0; HAND_SIZE];
+    for (potential, card) in potential.iter_mut().zip(hand) {
+        let Some(card) = *card else { continue };
+        let card = OwnedCard {
+            owner: player,
+            card,
+        };
+
+        *potential = (0..BOARD_SIZE)
+            .filter(|&cell| state.board[cell] == Cell::Empty)
+            .map(|cell| count_interactions(&state.board, card, cell))
+            .max()
+            .unwrap_or(0);
+    }
+    potential
+}
+
+// returns which empty cells either player could use to flip or battle an opponent card
+pub(crate) fn get_contested_cells(state: &GameState) -> [bool; BOARD_SIZE] {
+    let can_interact = |player: Player, hand: &[Option<Card>], cell| {
+        hand.iter().flatten().any(|&card| {
+            let card = OwnedCard {
+                owner: player,
+                card,
+            };
+            count_interactions(&state.board, card, cell) > 0
+        })
+    };
+
+    let mut contested = [false; BOARD_SIZE];
+    for (cell, contested) in contested.iter_mut().enumerate() {
+        *contested = state.board[cell] == Cell::Empty
+            && (can_interact(Player::P1, &state.p1_hand, cell)
+                || can_interact(Player::P2, &state.p2_hand, cell));
+    }
+    contested
+}
+
+// returns the number of opponent cards the card would flip or battle if placed on the cell
+fn count_interactions(board: &[Cell; BOARD_SIZE], card: OwnedCard, cell: usize) -> usize {
+    let arrows =
+        get_possible_neighbours(cell)
+            .iter()
+            .fold(Arrows(0), |arrows, &(neighbour, arrow)| {
+                match board[neighbour] {
+                    Cell::Card(defender) if does_interact(card, defender, arrow) => arrows | arrow,
+                    _ => arrows,
+                }
+            });
+    arrows.count() as usize
+}
+
+// returns the number of opponent cards the player could flip on their next move, using any card
+// in their hand on any empty cell, battles only count when the player is more likely to win them
+pub(crate) fn get_tempo(state: &GameState, player: Player) -> usize {
+    let hand = match player {
+        Player::P1 => &state.p1_hand,
+        Player::P2 => &state.p2_hand,
+    };
+
+    let mut threatened = [false; BOARD_SIZE];
+    for card in hand.iter().flatten() {
+        let card = OwnedCard {
+            owner: player,
+            card: *card,
+        };
+
+        for cell in 0..BOARD_SIZE {
+            if state.board[cell] != Cell::Empty {
+                continue;
+            }
+
+            for &(neighbour, arrow) in get_possible_neighbours(cell) {
+                if let Cell::Card(defender) = state.board[neighbour] {
+                    if !does_interact(card, defender, arrow) {
+                        continue;
+                    }
+                    let (win, _, _) = get_battle_odds(card.card, defender.card);
+                    threatened[neighbour] |= !is_standoff(defender.card, arrow) || win > 0.5;
+                }
+            }
+        }
+    }
+
+    threatened.iter().filter(|&&threatened| threatened).count()
+}
+
+// returns true if the card in the given cell can never be flipped again
+// a card can only be flipped by a card placed next to it or through a combo from a
+// neighbouring card that points at it, which in turn needs an empty cell next to it
+pub(crate) fn is_locked(state: &GameState, cell: usize) -> bool {
+    // a pending attacker can still lose its battles, so treat it like a card that is about to
+    // be placed on an empty cell
+    let mut board = state.board;
+    if let GameStatus::WaitingBattle { attacker_cell, .. } = state.status {
+        board[attacker_cell] = Cell::Empty;
+    }
+
+    let has_empty_neighbour = |cell| {
+        get_possible_neighbours(cell)
+            .iter()
+            .any(|&(neighbour, _)| board[neighbour] == Cell::Empty)
+    };
+
+    if !matches!(board[cell], Cell::Card(_)) || has_empty_neighbour(cell) {
+        return false;
+    }
+
+    get_possible_neighbours(cell)
+        .iter()
+        .all(|&(neighbour, arrow)| match board[neighbour] {
+            Cell::Card(card) if card.card.arrows.has(arrow.reverse()) => {
+                !has_empty_neighbour(neighbour)
+            }
+            _ => true,
+        })
+}
+
+// returns the arrows of the card in the given cell that point at an opponent card
+pub(crate) fn engaged_arrows(board: &[Cell; BOARD_SIZE], cell: usize) -> Arrows {
+    let Cell::Card(card) = board[cell] else {
+        return Arrows(0);
+    };
+
+    get_possible_neighbours(cell)
+        .iter()
+        .fold(Arrows(0), |engaged, &(neighbour, arrow)| {
+            match board[neighbour] {
+                Cell::Card(other) if does_interact(card, other, arrow) => engaged | arrow,
+                _ => engaged,
+            }
+        })
+}
+
+// returns true if the defender points back at an attacker that interacts with it, which makes
+// them battle instead of the attacker simply flipping the defender
+fn is_standoff(defender: Card, arrow_to_defender: Arrows) -> bool {
+    defender.arrows.has(arrow_to_defender.reverse())
+}
+
+// returns true if the card in the given cell and an opponent card next to it point at each other
+pub(crate) fn has_standoff(board: &[Cell; BOARD_SIZE], cell: usize) -> bool {
+    let Cell::Card(card) = board[cell] else {
+        return false;
+    };
+
+    get_possible_neighbours(cell)
+        .iter()
+        .any(|&(neighbour, arrow)| match board[neighbour] {
+            Cell::Card(other) => {
+                does_interact(card, other, arrow) && is_standoff(other.card, arrow)
+            }
+            _ => false,
+        })
+}
+
 fn does_interact(attacker: OwnedCard, defender: OwnedCard, arrow_to_defender: Arrows) -> bool {
     // they don't interact if both cards belong to the same player
     if defender.owner == attacker.owner {
@@ -1,4 +1,5 @@
 use super::*;
+use crate::MAX_NUMBER_OF_BLOCKS;
 use pretty_assertions::assert_eq;
 
 // board cells references
@@ -23,35 +24,61 @@ impl GameState {
     fn empty() -> Self {
         let card = Some(Card::basic());
         GameState {
+            seed: 0,
+            max_blocks: 0,
             status: GameStatus::WaitingPlace,
             rng: fastrand::Rng::with_seed(0),
             turn: Player::P1,
+            turn_number: 1,
+            move_count: 0,
             board: Default::default(),
             p1_hand: [card, card, card, card, card],
             p2_hand: [card, card, card, card, card],
         }
     }
+
+    // builder methods for setting up arbitrary mid-game states on top of GameState::empty
+
+    fn turn(mut self, turn: Player) -> Self {
+        self.turn = turn;
+        self
+    }
+
+    fn p1_hand(mut self, cards: &[Card]) -> Self {
+        self.p1_hand = hand(cards);
+        self
+    }
+
+    fn p2_hand(mut self, cards: &[Card]) -> Self {
+        self.p2_hand = hand(cards);
+        self
+    }
+
+    fn cell(mut self, cell: usize, value: Cell) -> Self {
+        self.board[cell] = value;
+        self
+    }
+
+    fn blocked(mut self, cells: &[usize]) -> Self {
+        for &cell in cells {
+            self.board[cell] = Cell::Blocked;
+        }
+        self
+    }
+}
+
+// fills a hand from the front leaving the rest of the hand empty
+fn hand(cards: &[Card]) -> [Option<Card>; HAND_SIZE] {
+    let mut hand = [None; HAND_SIZE];
+    for (slot, &card) in hand.iter_mut().zip(cards) {
+        *slot = Some(card);
+    }
+    hand
 }
 
 impl Card {
     fn from_str(stats: &str, arrows: Arrows) -> Self {
-        let card_type = match &stats[1..2] {
-            "P" => CardType::Physical,
-            "M" => CardType::Magical,
-            "X" => CardType::Exploit,
-            "A" => CardType::Assault,
-            _ => unreachable!(),
-        };
-        let attack = 0xF + 0x10 * u8::from_str_radix(&stats[0..1], 16).unwrap();
-        let physical_defense = 0xF + 0x10 * u8::from_str_radix(&stats[2..3], 16).unwrap();
-        let magical_defense = 0xF + 0x10 * u8::from_str_radix(&stats[3..4], 16).unwrap();
-        Card {
-            card_type,
-            attack,
-            physical_defense,
-            magical_defense,
-            arrows,
-        }
+        Card::try_parse(&format!("{stats}_{:X}", arrows.0)).unwrap()
     }
 
     fn basic() -> Self {
@@ -98,6 +125,34 @@ impl Input {
     }
 }
 
+// asserts that every flip in the log starts from the owner the card had at that point
+// cards already on the board before the log starts are taken as they were first seen
+fn assert_log_is_consistent(log: &GameLog) {
+    let mut owners: [Option<Player>; BOARD_SIZE] = Default::default();
+    for entry in log.iter() {
+        match entry {
+            Entry::PlaceCard { card, cell } => {
+                assert_eq!(owners[*cell], None, "card placed on occupied cell {cell:X}");
+                owners[*cell] = Some(card.owner);
+            }
+            Entry::FlipCard { card, cell, to, .. } => {
+                let owner = owners[*cell].unwrap_or(card.owner);
+                assert_eq!(
+                    card.owner, owner,
+                    "cell {cell:X} flipped from the wrong owner"
+                );
+                assert_eq!(
+                    *to,
+                    owner.opposite(),
+                    "cell {cell:X} flipped to the wrong owner"
+                );
+                owners[*cell] = Some(*to);
+            }
+            Entry::NextTurn { .. } | Entry::Battle { .. } => {}
+        }
+    }
+}
+
 #[test]
 fn turn_should_change_after_a_valid_play() {
     let mut state = GameState::empty();
@@ -111,6 +166,77 @@ fn turn_should_change_after_a_valid_play() {
     assert_eq!(state.turn, Player::P2);
 }
 
+#[test]
+fn count_turns_and_moves() {
+    let mut state = GameState::empty();
+    let mut log = GameLog::new(state.turn);
+
+    let card_points_down = Card::from_str("0P00", Arrows::DOWN);
+    let card_points_up = Card::from_str("0P00", Arrows::UP);
+    let card_points_vert = Card::from_str("FP00", Arrows::UP | Arrows::DOWN);
+    state.p1_hand[0] = Some(card_points_vert);
+    state.board[0] = Cell::p2_card(card_points_down);
+    state.board[8] = Cell::p2_card(card_points_up);
+
+    // rejected inputs aren't counted
+    next(&mut state, &mut log, Input::battle(0)).unwrap_err();
+    assert_eq!((state.turn_number, state.move_count), (1, 0));
+
+    next(&mut state, &mut log, Input::place(0, 4)).unwrap();
+    assert_eq!((state.turn_number, state.move_count), (1, 1));
+
+    // battle choices aren't placements
+    next(&mut state, &mut log, Input::battle(0)).unwrap();
+    assert_eq!((state.turn_number, state.move_count), (2, 1));
+
+    next(&mut state, &mut log, Input::place(0, 1)).unwrap();
+    assert_eq!((state.turn_number, state.move_count), (3, 2));
+}
+
+#[test]
+fn count_remaining_moves() {
+    let card_points_down = Card::from_str("0P00", Arrows::DOWN);
+    let card_points_up = Card::from_str("0P00", Arrows::UP);
+    let card_points_vert = Card::from_str("FP00", Arrows::UP | Arrows::DOWN);
+    let mut state = GameState::empty()
+        .p1_hand(&[card_points_vert, Card::basic()])
+        .p2_hand(&[Card::basic()])
+        .cell(0, Cell::p2_card(card_points_down))
+        .cell(8, Cell::p2_card(card_points_up));
+    let mut log = GameLog::new(state.turn);
+
+    assert_eq!(get_remaining_moves(&state), 3);
+
+    // the pending battle choice isn't counted, only the cards left in hand
+    next(&mut state, &mut log, Input::place(0, 4)).unwrap();
+    assert!(matches!(state.status, GameStatus::WaitingBattle { .. }));
+    assert_eq!(get_remaining_moves(&state), 2);
+
+    next(&mut state, &mut log, Input::battle(0)).unwrap();
+    assert_eq!(get_remaining_moves(&state), 2);
+}
+
+#[test]
+fn go_through_every_phase() {
+    let mut state = GameState::empty();
+    let mut log = GameLog::new(state.turn);
+
+    let mut phases = vec![get_phase(&state)];
+    for cell in 0..2 * HAND_SIZE {
+        next(&mut state, &mut log, Input::place(cell / 2, cell)).unwrap();
+        phases.push(get_phase(&state));
+    }
+
+    use GamePhase::*;
+    assert_eq!(
+        phases,
+        vec![
+            Opening, Opening, Opening, Midgame, Midgame, Midgame, Midgame, Endgame, Endgame,
+            Endgame, Endgame,
+        ]
+    );
+}
+
 #[test]
 fn reject_input_if_the_card_has_already_been_played() {
     let mut state = GameState::empty();
@@ -149,6 +275,52 @@ fn reject_input_if_the_cell_played_on_already_has_a_card_placed() {
     assert_eq!(res, Err("Cell 3 is not empty".into()));
 }
 
+#[test]
+fn reject_input_if_there_are_no_legal_moves() {
+    let mut state = GameState::empty();
+    let mut log = GameLog::new(state.turn);
+
+    state.p1_hand = [None, None, None, None, None];
+    let res = next(&mut state, &mut log, Input::place(0, 0));
+    assert_eq!(res, Err("No cards left in hand to place".into()));
+
+    state.p1_hand[0] = Some(Card::basic());
+    state.board = [Cell::Blocked; BOARD_SIZE];
+    let res = next(&mut state, &mut log, Input::place(0, 0));
+    assert_eq!(res, Err("No empty cells left to place a card on".into()));
+}
+
+#[test]
+fn reject_input_if_it_doesnt_match_the_game_status() {
+    let mut state = GameState::empty();
+    let mut log = GameLog::new(state.turn);
+
+    let res = next(&mut state, &mut log, Input::battle(0));
+    assert_eq!(
+        res,
+        Err("Expected a card to place, not a battle choice".into())
+    );
+
+    state.status = GameStatus::WaitingBattle {
+        attacker_cell: 0,
+        choices: vec![],
+        placed_from: None,
+    };
+    let res = next(&mut state, &mut log, Input::place(0, 0));
+    assert_eq!(
+        res,
+        Err("Expected a battle choice, not a card to place".into())
+    );
+
+    state.status = GameStatus::GameOver {
+        winner: None,
+        p1_cards: 0,
+        p2_cards: 0,
+    };
+    let res = next(&mut state, &mut log, Input::place(0, 0));
+    assert_eq!(res, Err("Game is already over".into()));
+}
+
 #[test]
 fn move_card_from_hand_to_board_if_input_is_valid() {
     let mut state = GameState::empty();
@@ -223,12 +395,34 @@ fn update_game_log_on_flipping_cards() {
         vec![
             &Entry::next_turn(Player::P1),
             &Entry::place_card(OwnedCard::p1(card_points_up), 4),
-            &Entry::flip_card(OwnedCard::p2(card_no_arrows), 0, Player::P1, false),
+            &Entry::flip_card(OwnedCard::p2(card_no_arrows), 0, Player::P1, FlipKind::Free),
             &Entry::next_turn(Player::P2),
         ]
     );
 }
 
+#[test]
+fn game_log_score_deltas_should_add_up_to_the_cards_on_the_board() {
+    let mut state = GameState::empty();
+    let mut log = GameLog::new(state.turn);
+
+    let card_no_arrows = Card::basic_with(Arrows::NONE);
+    let card_points_up = Card::basic_with(Arrows::UP | Arrows::RIGHT);
+    state.p1_hand[0] = Some(card_no_arrows);
+    state.p2_hand[0] = Some(card_no_arrows);
+    state.p1_hand[1] = Some(card_points_up);
+
+    next(&mut state, &mut log, Input::place(0, 5)).unwrap();
+    next(&mut state, &mut log, Input::place(0, 0)).unwrap();
+    next(&mut state, &mut log, Input::place(1, 4)).unwrap();
+
+    let score = log
+        .iter()
+        .map(Entry::score_delta)
+        .fold((0, 0), |(p1, p2), (d1, d2)| (p1 + d1, p2 + d2));
+    assert_eq!(score, (3, 0));
+}
+
 #[test]
 fn battle_cards_that_belong_to_opponent_are_pointed_to_and_point_back() {
     let mut state = GameState::empty();
@@ -323,7 +517,12 @@ fn update_game_log_on_battles() {
                         },
                     }
                 ),
-                &Entry::flip_card(OwnedCard::p2(card_points_down), 0, Player::P1, false),
+                &Entry::flip_card(
+                    OwnedCard::p2(card_points_down),
+                    0,
+                    Player::P1,
+                    FlipKind::Battle
+                ),
                 &Entry::next_turn(Player::P2),
             ]
         );
@@ -362,7 +561,12 @@ fn update_game_log_on_battles() {
                         },
                     }
                 ),
-                &Entry::flip_card(OwnedCard::p1(card_points_up), 4, Player::P2, false),
+                &Entry::flip_card(
+                    OwnedCard::p1(card_points_up),
+                    4,
+                    Player::P2,
+                    FlipKind::Battle
+                ),
                 &Entry::next_turn(Player::P2),
             ]
         );
@@ -401,7 +605,12 @@ fn update_game_log_on_battles() {
                         },
                     }
                 ),
-                &Entry::flip_card(OwnedCard::p1(card_points_up), 4, Player::P2, false),
+                &Entry::flip_card(
+                    OwnedCard::p1(card_points_up),
+                    4,
+                    Player::P2,
+                    FlipKind::Battle
+                ),
                 &Entry::next_turn(Player::P2),
             ]
         );
@@ -454,10 +663,30 @@ fn flip_other_undefended_cards_after_attacker_wins_battle() {
                     },
                 }
             ),
-            &Entry::flip_card(OwnedCard::p2(card_points_down), 0, Player::P1, false),
-            &Entry::flip_card(OwnedCard::p2(card_points_none), 1, Player::P1, false),
-            &Entry::flip_card(OwnedCard::p2(card_points_none), 5, Player::P1, false),
-            &Entry::flip_card(OwnedCard::p2(card_points_none), 9, Player::P1, false),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_down),
+                0,
+                Player::P1,
+                FlipKind::Battle
+            ),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_none),
+                1,
+                Player::P1,
+                FlipKind::Free
+            ),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_none),
+                5,
+                Player::P1,
+                FlipKind::Free
+            ),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_none),
+                9,
+                Player::P1,
+                FlipKind::Free
+            ),
             &Entry::next_turn(Player::P2),
         ]
     );
@@ -509,7 +738,12 @@ fn dont_flip_other_undefended_cards_after_attacker_loses_battle() {
                     },
                 }
             ),
-            &Entry::flip_card(OwnedCard::p1(card_points_all), 4, Player::P2, false),
+            &Entry::flip_card(
+                OwnedCard::p1(card_points_all),
+                4,
+                Player::P2,
+                FlipKind::Battle
+            ),
             &Entry::next_turn(Player::P2),
         ]
     );
@@ -533,7 +767,8 @@ fn change_status_to_chose_battle_when_multiple_battles_are_available() {
         state.status,
         GameStatus::WaitingBattle {
             attacker_cell: 4,
-            choices: vec![(0, card_points_down), (8, card_points_up)]
+            choices: vec![(0, card_points_down), (8, card_points_up)],
+            placed_from: Some(0),
         }
     );
 }
@@ -607,7 +842,12 @@ fn continue_after_battle_choice_is_given() {
                     },
                 }
             ),
-            &Entry::flip_card(OwnedCard::p2(card_points_up), 8, Player::P1, false),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_up),
+                8,
+                Player::P1,
+                FlipKind::Battle
+            ),
             &Entry::battle(
                 OwnedCard::p1(card_points_vert),
                 OwnedCard::p2(card_points_down),
@@ -625,7 +865,12 @@ fn continue_after_battle_choice_is_given() {
                     },
                 }
             ),
-            &Entry::flip_card(OwnedCard::p2(card_points_down), 0, Player::P1, false),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_down),
+                0,
+                Player::P1,
+                FlipKind::Battle
+            ),
             &Entry::next_turn(Player::P2),
         ]
     );
@@ -649,6 +894,96 @@ fn reject_input_if_the_choice_isnt_valid() {
     assert_eq!(res, Err("Cell 4 is not a valid choice".into()));
 }
 
+#[test]
+fn take_back_card_if_battle_choice_is_cancelled() {
+    let card_points_down = Card::from_str("0P10", Arrows::DOWN);
+    let card_points_up = Card::from_str("0P10", Arrows::UP);
+    let card_points_vert = Card::from_str("1P00", Arrows::UP | Arrows::DOWN);
+    let mut state = GameState::empty()
+        .p1_hand(&[Card::basic(), card_points_vert])
+        .cell(0, Cell::p2_card(card_points_down))
+        .cell(8, Cell::p2_card(card_points_up));
+    let mut log = GameLog::new(state.turn);
+
+    next(&mut state, &mut log, Input::place(1, 4)).unwrap();
+    next(&mut state, &mut log, Input::Cancel).unwrap();
+
+    assert_eq!(state.status, GameStatus::WaitingPlace);
+    assert_eq!(state.turn, Player::P1);
+    assert_eq!(state.board[4], Cell::Empty);
+    assert_eq!(state.p1_hand[1], Some(card_points_vert));
+    assert_eq!(state.move_count, 0);
+    let log: Vec<_> = log.iter().collect();
+    assert_eq!(log, vec![&Entry::next_turn(Player::P1)]);
+}
+
+#[test]
+fn place_a_different_card_after_cancelling() {
+    let card_points_down = Card::from_str("0P10", Arrows::DOWN);
+    let card_points_up = Card::from_str("0P10", Arrows::UP);
+    let card_points_vert = Card::from_str("1P00", Arrows::UP | Arrows::DOWN);
+    let mut state = GameState::empty()
+        .p1_hand(&[Card::basic(), card_points_vert])
+        .cell(0, Cell::p2_card(card_points_down))
+        .cell(8, Cell::p2_card(card_points_up));
+    let mut log = GameLog::new(state.turn);
+
+    next(&mut state, &mut log, Input::place(1, 4)).unwrap();
+    next(&mut state, &mut log, Input::Cancel).unwrap();
+    next(&mut state, &mut log, Input::place(0, 1)).unwrap();
+
+    assert_eq!(state.status, GameStatus::WaitingPlace);
+    assert_eq!(state.turn, Player::P2);
+    assert_eq!(state.board[1], Cell::p1_card(Card::basic()));
+    assert_eq!(state.board[4], Cell::Empty);
+    assert_eq!(state.p1_hand[0], None);
+    assert_eq!(state.p1_hand[1], Some(card_points_vert));
+    assert_eq!(state.move_count, 1);
+    let log: Vec<_> = log.iter().collect();
+    assert_eq!(
+        log,
+        vec![
+            &Entry::next_turn(Player::P1),
+            &Entry::place_card(OwnedCard::p1(Card::basic()), 1),
+            &Entry::next_turn(Player::P2),
+        ]
+    );
+}
+
+#[test]
+fn reject_cancel_when_waiting_for_a_placement() {
+    let mut state = GameState::empty();
+    let mut log = GameLog::new(state.turn);
+
+    let res = next(&mut state, &mut log, Input::Cancel);
+
+    assert_eq!(res, Err("There is no placement to take back".into()));
+    assert_eq!(state.status, GameStatus::WaitingPlace);
+}
+
+#[test]
+fn reject_cancel_once_a_battle_has_been_fought() {
+    let card_points_down = Card::from_str("0P00", Arrows::DOWN);
+    let card_points_left = Card::from_str("0P00", Arrows::LEFT);
+    let card_points_up = Card::from_str("0P00", Arrows::UP);
+    let card_points_all = Card::from_str("FP00", Arrows::ALL);
+    let mut state = GameState::empty()
+        .p1_hand(&[card_points_all])
+        .cell(0, Cell::p2_card(card_points_down))
+        .cell(5, Cell::p2_card(card_points_left))
+        .cell(8, Cell::p2_card(card_points_up));
+    let mut log = GameLog::new(state.turn);
+
+    next(&mut state, &mut log, Input::place(0, 4)).unwrap();
+    next(&mut state, &mut log, Input::battle(0)).unwrap();
+    let res = next(&mut state, &mut log, Input::Cancel);
+
+    assert_eq!(
+        res,
+        Err("Card can't be taken back after a battle has been fought".into())
+    );
+}
+
 #[test]
 fn continue_offering_choices_when_multiple_battles_are_still_available() {
     let mut state = GameState::empty();
@@ -670,7 +1005,8 @@ fn continue_offering_choices_when_multiple_battles_are_still_available() {
         state.status,
         GameStatus::WaitingBattle {
             attacker_cell: 4,
-            choices: vec![(5, card_points_left), (8, card_points_up)]
+            choices: vec![(5, card_points_left), (8, card_points_up)],
+            placed_from: None,
         }
     );
 }
@@ -720,7 +1056,9 @@ fn handle_game_over_when_attacker_loses_battle_after_battle_choice() {
     assert_eq!(
         state.status,
         GameStatus::GameOver {
-            winner: Some(Player::P2)
+            winner: Some(Player::P2),
+            p1_cards: 0,
+            p2_cards: 4,
         }
     );
     assert_eq!(state.board[0], Cell::p2_card(card_points_down));
@@ -729,6 +1067,92 @@ fn handle_game_over_when_attacker_loses_battle_after_battle_choice() {
     assert_eq!(state.board[4], Cell::p2_card(card_points_all));
 }
 
+#[test]
+fn change_turn_when_attacker_loses_battle_after_battle_choice() {
+    let mut state = GameState::empty();
+    let mut log = GameLog::new(state.turn);
+
+    let card_points_down = Card::from_str("0PF0", Arrows::DOWN);
+    let card_points_up = Card::from_str("0P00", Arrows::UP);
+    let card_points_vert = Card::from_str("0P00", Arrows::UP | Arrows::DOWN);
+    state.p1_hand[0] = Some(card_points_vert);
+    state.board[0] = Cell::p2_card(card_points_down);
+    state.board[8] = Cell::p2_card(card_points_up);
+
+    next(&mut state, &mut log, Input::place(0, 4)).unwrap();
+    next(&mut state, &mut log, Input::battle(0)).unwrap();
+
+    assert_eq!(state.turn, Player::P2);
+    assert_eq!(log.iter().last(), Some(&Entry::next_turn(Player::P2)));
+}
+
+#[test]
+fn game_over_replaces_next_turn_when_attacker_loses_battle_after_battle_choice() {
+    let card_points_down = Card::from_str("0PF0", Arrows::DOWN);
+    let card_points_up = Card::from_str("0P00", Arrows::UP);
+    let card_points_vert = Card::from_str("0P00", Arrows::UP | Arrows::DOWN);
+    let mut state = GameState::empty()
+        .p1_hand(&[card_points_vert])
+        .p2_hand(&[])
+        .cell(0, Cell::p2_card(card_points_down))
+        .cell(8, Cell::p2_card(card_points_up));
+    let mut log = GameLog::new(state.turn);
+
+    next(&mut state, &mut log, Input::place(0, 4)).unwrap();
+    next(&mut state, &mut log, Input::battle(0)).unwrap();
+
+    assert!(matches!(state.status, GameStatus::GameOver { .. }));
+    assert!(log
+        .iter()
+        .skip(1)
+        .all(|entry| !matches!(entry, Entry::NextTurn { .. })));
+}
+
+#[test]
+fn combo_flip_back_cards_flipped_earlier_in_the_turn_if_attacker_loses_later_battle() {
+    let attacker = Card {
+        attack: 0x10,
+        ..Card::basic_with(Arrows::ALL)
+    };
+    let weak_defender = Card {
+        physical_defense: 0x00,
+        ..Card::basic_with(Arrows::DOWN)
+    };
+    let strong_defender = Card {
+        physical_defense: 0xFF,
+        ..Card::basic_with(Arrows::UP)
+    };
+    let mut state = GameState::empty()
+        .p1_hand(&[attacker])
+        .cell(0, Cell::p2_card(weak_defender))
+        .cell(8, Cell::p2_card(strong_defender));
+    let mut log = GameLog::new(state.turn);
+
+    next(&mut state, &mut log, Input::place(0, 4)).unwrap();
+    next(&mut state, &mut log, Input::battle(0)).unwrap();
+
+    assert_eq!(state.board[0], Cell::p2_card(weak_defender));
+    assert_eq!(state.board[4], Cell::p2_card(attacker));
+    assert_eq!(state.board[8], Cell::p2_card(strong_defender));
+
+    let flips: Vec<_> = log
+        .iter()
+        .filter_map(|entry| match entry {
+            Entry::FlipCard { cell, kind, .. } => Some((*cell, *kind)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        flips,
+        vec![
+            (0, FlipKind::Battle),
+            (4, FlipKind::Battle),
+            (0, FlipKind::Combo),
+        ]
+    );
+    assert_log_is_consistent(&log);
+}
+
 #[test]
 fn combo_flip_cards_that_are_pointed_to_by_defender_if_they_lose() {
     let mut state = GameState::empty();
@@ -773,10 +1197,30 @@ fn combo_flip_cards_that_are_pointed_to_by_defender_if_they_lose() {
                     },
                 }
             ),
-            &Entry::flip_card(OwnedCard::p2(card_points_all), 5, Player::P1, false),
-            &Entry::flip_card(OwnedCard::p2(card_points_none), 1, Player::P1, true),
-            &Entry::flip_card(OwnedCard::p2(card_points_none), 6, Player::P1, true),
-            &Entry::flip_card(OwnedCard::p2(card_points_none), 4, Player::P1, true),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_all),
+                5,
+                Player::P1,
+                FlipKind::Battle
+            ),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_none),
+                1,
+                Player::P1,
+                FlipKind::Combo
+            ),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_none),
+                6,
+                Player::P1,
+                FlipKind::Combo
+            ),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_none),
+                4,
+                Player::P1,
+                FlipKind::Combo
+            ),
             &Entry::next_turn(Player::P2),
         ]
     );
@@ -826,10 +1270,91 @@ fn combo_flip_cards_that_are_pointed_to_by_attacker_if_they_lose() {
                     },
                 }
             ),
-            &Entry::flip_card(OwnedCard::p1(card_points_all), 5, Player::P2, false),
-            &Entry::flip_card(OwnedCard::p1(card_points_none), 1, Player::P2, true),
-            &Entry::flip_card(OwnedCard::p1(card_points_none), 6, Player::P2, true),
-            &Entry::flip_card(OwnedCard::p1(card_points_none), 4, Player::P2, true),
+            &Entry::flip_card(
+                OwnedCard::p1(card_points_all),
+                5,
+                Player::P2,
+                FlipKind::Battle
+            ),
+            &Entry::flip_card(
+                OwnedCard::p1(card_points_none),
+                1,
+                Player::P2,
+                FlipKind::Combo
+            ),
+            &Entry::flip_card(
+                OwnedCard::p1(card_points_none),
+                6,
+                Player::P2,
+                FlipKind::Combo
+            ),
+            &Entry::flip_card(
+                OwnedCard::p1(card_points_none),
+                4,
+                Player::P2,
+                FlipKind::Combo
+            ),
+            &Entry::next_turn(Player::P2),
+        ]
+    );
+}
+
+#[test]
+fn dont_combo_flip_cards_that_are_pointed_to_by_free_flipped_cards() {
+    let mut state = GameState::empty();
+    let mut log = GameLog::new(state.turn);
+
+    let card_points_up_right = Card::from_str("FP00", Arrows::UP | Arrows::RIGHT);
+    let card_points_down = Card::from_str("0P00", Arrows::DOWN);
+    let card_points_right = Card::from_str("0P00", Arrows::RIGHT);
+    let card_points_none = Card::from_str("0P00", Arrows::NONE);
+    state.p1_hand[0] = Some(card_points_up_right);
+    state.board[5] = Cell::p2_card(card_points_down);
+    state.board[0xA] = Cell::p2_card(card_points_right);
+    state.board[0xB] = Cell::p2_card(card_points_none);
+
+    // attacker wins the battle against 5 and free flips A, which points at B
+    next(&mut state, &mut log, Input::place(0, 9)).unwrap();
+
+    assert_eq!(state.board[5], Cell::p1_card(card_points_down));
+    assert_eq!(state.board[0xA], Cell::p1_card(card_points_right));
+    assert_eq!(state.board[0xB], Cell::p2_card(card_points_none));
+
+    let log: Vec<_> = log.iter().collect();
+    assert_eq!(
+        log,
+        vec![
+            &Entry::next_turn(Player::P1),
+            &Entry::place_card(OwnedCard::p1(card_points_up_right), 9),
+            &Entry::battle(
+                OwnedCard::p1(card_points_up_right),
+                OwnedCard::p2(card_points_down),
+                BattleResult {
+                    winner: BattleWinner::Attacker,
+                    attack_stat: BattleStat {
+                        digit: 0,
+                        value: 0xFF,
+                        roll: 142
+                    },
+                    defense_stat: BattleStat {
+                        digit: 2,
+                        value: 0x0F,
+                        roll: 15
+                    },
+                }
+            ),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_down),
+                5,
+                Player::P1,
+                FlipKind::Battle
+            ),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_right),
+                0xA,
+                Player::P1,
+                FlipKind::Free
+            ),
             &Entry::next_turn(Player::P2),
         ]
     );
@@ -878,8 +1403,18 @@ fn dont_flip_back_undefended_cards_if_they_are_flipped_due_to_combos() {
                     },
                 }
             ),
-            &Entry::flip_card(OwnedCard::p2(card_points_all_def), 0, Player::P1, false),
-            &Entry::flip_card(OwnedCard::p2(card_points_none), 4, Player::P1, true),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_all_def),
+                0,
+                Player::P1,
+                FlipKind::Battle
+            ),
+            &Entry::flip_card(
+                OwnedCard::p2(card_points_none),
+                4,
+                Player::P1,
+                FlipKind::Combo
+            ),
             &Entry::next_turn(Player::P2),
         ]
     );
@@ -910,7 +1445,9 @@ fn game_should_be_over_once_all_cards_have_been_played() {
         assert_eq!(
             state.status,
             GameStatus::GameOver {
-                winner: Some(Player::P1)
+                winner: Some(Player::P1),
+                p1_cards: 2,
+                p2_cards: 1,
             }
         );
     }
@@ -932,7 +1469,9 @@ fn game_should_be_over_once_all_cards_have_been_played() {
         assert_eq!(
             state.status,
             GameStatus::GameOver {
-                winner: Some(Player::P2)
+                winner: Some(Player::P2),
+                p1_cards: 1,
+                p2_cards: 2,
             }
         );
     }
@@ -949,10 +1488,82 @@ fn game_should_be_over_once_all_cards_have_been_played() {
         next(&mut state, &mut log, Input::place(0, 0)).unwrap();
         next(&mut state, &mut log, Input::place(0, 1)).unwrap();
 
-        assert_eq!(state.status, GameStatus::GameOver { winner: None });
+        assert_eq!(
+            state.status,
+            GameStatus::GameOver {
+                winner: None,
+                p1_cards: 1,
+                p2_cards: 1,
+            }
+        );
     }
 }
 
+#[test]
+fn game_should_be_over_once_all_cards_have_been_played_even_if_cells_remain_empty() {
+    let card = Card::from_str("0P00", Arrows::NONE);
+    let mut state = GameState::empty()
+        .turn(Player::P1)
+        .p1_hand(&[card])
+        .p2_hand(&[])
+        .blocked(&[0x0, 0x3, 0x5, 0xA, 0xC, 0xF])
+        .cell(0x1, Cell::p1_card(card))
+        .cell(0x2, Cell::p2_card(card))
+        .cell(0x4, Cell::p2_card(card))
+        .cell(0x6, Cell::p2_card(card));
+    let mut log = GameLog::new(state.turn);
+
+    next(&mut state, &mut log, Input::place(0, 0x7)).unwrap();
+
+    // only the placed cards count towards the score
+    assert_eq!(
+        state.status,
+        GameStatus::GameOver {
+            winner: Some(Player::P2),
+            p1_cards: 2,
+            p2_cards: 3,
+        }
+    );
+    assert!(state.board.contains(&Cell::Empty));
+
+    // the final placement doesn't start another turn
+    let log: Vec<_> = log.iter().collect();
+    assert_eq!(
+        log,
+        vec![
+            &Entry::next_turn(Player::P1),
+            &Entry::place_card(OwnedCard::p1(card), 0x7),
+        ]
+    );
+}
+
+#[test]
+fn game_should_be_over_once_the_board_is_full_even_if_cards_remain_in_hand() {
+    let card = Card::from_str("0P00", Arrows::NONE);
+    let mut state = GameState::empty()
+        .turn(Player::P1)
+        .p1_hand(&[card, card, card])
+        .p2_hand(&[card, card])
+        .blocked(&[0x0, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0x8, 0x9, 0xA, 0xB]);
+    let mut log = GameLog::new(state.turn);
+
+    next(&mut state, &mut log, Input::place(0, 0xC)).unwrap();
+    next(&mut state, &mut log, Input::place(0, 0xD)).unwrap();
+    next(&mut state, &mut log, Input::place(1, 0xE)).unwrap();
+    assert_eq!(state.status, GameStatus::WaitingPlace);
+    next(&mut state, &mut log, Input::place(1, 0xF)).unwrap();
+
+    assert_eq!(
+        state.status,
+        GameStatus::GameOver {
+            winner: None,
+            p1_cards: 2,
+            p2_cards: 2,
+        }
+    );
+    assert_eq!(state.p1_hand[2], Some(card));
+}
+
 #[cfg(test)]
 mod test_get_attack_stat {
     use super::*;
@@ -1061,3 +1672,888 @@ mod test_get_defense_stat {
         assert_eq!(stat.value, 0xAF);
     }
 }
+
+#[cfg(test)]
+mod test_is_locked {
+    use super::*;
+
+    #[test]
+    fn empty_and_blocked_cells_are_not_locked() {
+        let mut state = GameState::empty();
+        state.board[0] = Cell::Blocked;
+
+        assert!(!is_locked(&state, 0));
+        assert!(!is_locked(&state, 5));
+    }
+
+    #[test]
+    fn card_with_an_empty_neighbour_is_not_locked() {
+        let mut state = GameState::empty();
+        state.board[0] = Cell::p1_card(Card::basic());
+        state.board[1] = Cell::Blocked;
+        state.board[4] = Cell::p2_card(Card::basic());
+
+        assert!(!is_locked(&state, 0));
+    }
+
+    #[test]
+    fn card_surrounded_by_cards_and_edges_is_locked() {
+        let mut state = GameState::empty();
+        state.board[0] = Cell::p1_card(Card::basic());
+        state.board[1] = Cell::Blocked;
+        state.board[4] = Cell::p2_card(Card::basic());
+        state.board[5] = Cell::p2_card(Card::basic());
+
+        assert!(is_locked(&state, 0));
+    }
+
+    #[test]
+    fn card_pointed_to_by_a_neighbour_that_can_still_lose_a_battle_is_not_locked() {
+        let mut state = GameState::empty();
+        state.board[0] = Cell::p1_card(Card::basic());
+        state.board[1] = Cell::Blocked;
+        state.board[4] = Cell::p1_card(Card::basic_with(Arrows::UP));
+        state.board[5] = Cell::p2_card(Card::basic());
+
+        assert!(!is_locked(&state, 0));
+
+        // once the neighbour is surrounded it can no longer lose a battle
+        for cell in [8, 9] {
+            state.board[cell] = Cell::Blocked;
+        }
+
+        assert!(is_locked(&state, 0));
+    }
+
+    #[test]
+    fn card_next_to_a_pending_attacker_is_not_locked() {
+        let mut state = GameState::empty()
+            .blocked(&[0x1, 0x5, 0x8, 0x9])
+            .cell(0x0, Cell::p1_card(Card::basic()))
+            .cell(0x4, Cell::p1_card(Card::basic_with(Arrows::ALL)));
+        state.status = GameStatus::WaitingBattle {
+            attacker_cell: 0x4,
+            choices: vec![],
+            placed_from: None,
+        };
+
+        assert!(!is_locked(&state, 0x0));
+        assert!(!is_locked(&state, 0x4));
+    }
+}
+
+#[cfg(test)]
+mod test_battle_choice {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn card(attack: u8, physical_defense: u8, arrows: Arrows) -> Card {
+        Card {
+            attack,
+            physical_defense,
+            ..Card::basic_with(arrows)
+        }
+    }
+
+    // places a card that can battle both cards above and below it then picks the one above
+    fn battle(attack: u8, physical_defense: u8) -> (GameState, BattleResult) {
+        let mut state = GameState::empty();
+        let mut log = GameLog::new(state.turn);
+
+        state.p1_hand[0] = Some(card(attack, 0, Arrows::UP | Arrows::DOWN));
+        state.board[0] = Cell::p2_card(card(0, physical_defense, Arrows::DOWN));
+        state.board[8] = Cell::p2_card(card(0, physical_defense, Arrows::UP));
+
+        next(&mut state, &mut log, Input::place(0, 4)).unwrap();
+        next(&mut state, &mut log, Input::battle(0)).unwrap();
+
+        let result = log
+            .iter()
+            .find_map(|entry| match entry {
+                Entry::Battle { result, .. } => Some(*result),
+                _ => None,
+            })
+            .unwrap();
+        (state, result)
+    }
+
+    #[test]
+    fn attacker_wins() {
+        let (state, result) = battle(0xFF, 0x00);
+
+        assert_eq!(result.winner, BattleWinner::Attacker);
+        assert_eq!(result.attack_stat.digit, 0);
+        assert_eq!(result.defense_stat.digit, 2);
+        assert!(result.attack_stat.resolve() > result.defense_stat.resolve());
+        assert!(matches!(state.board[0], Cell::Card(card) if card.owner == Player::P1));
+        assert!(matches!(state.board[4], Cell::Card(card) if card.owner == Player::P1));
+    }
+
+    #[test]
+    fn defender_wins() {
+        let (state, result) = battle(0x00, 0xFF);
+
+        assert_eq!(result.winner, BattleWinner::Defender);
+        assert!(result.attack_stat.resolve() < result.defense_stat.resolve());
+        assert!(matches!(state.board[0], Cell::Card(card) if card.owner == Player::P2));
+        assert!(matches!(state.board[4], Cell::Card(card) if card.owner == Player::P2));
+    }
+
+    #[test]
+    fn draw_is_won_by_the_defender() {
+        let (state, result) = battle(0x00, 0x00);
+
+        assert_eq!(result.winner, BattleWinner::None);
+        assert_eq!(result.attack_stat.resolve(), result.defense_stat.resolve());
+        assert!(matches!(state.board[0], Cell::Card(card) if card.owner == Player::P2));
+        assert!(matches!(state.board[4], Cell::Card(card) if card.owner == Player::P2));
+    }
+}
+
+#[cfg(test)]
+mod test_most_threatening_card {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn pick_the_card_and_cell_that_reach_the_most_opponent_cards() {
+        let mut state = GameState::empty();
+        state.p2_hand = [
+            Some(Card::basic_with(Arrows::UP)),
+            None,
+            Some(Card::basic_with(Arrows::LEFT | Arrows::RIGHT)),
+            Some(Card::basic_with(Arrows::NONE)),
+            None,
+        ];
+        state.board[0x4] = Cell::p1_card(Card::basic());
+        state.board[0x6] = Cell::p1_card(Card::basic());
+        state.board[0x1] = Cell::p2_card(Card::basic());
+
+        let threat = most_threatening_card(&state, Player::P2);
+
+        assert_eq!(threat, Some((2, 0x5, 2)));
+    }
+
+    #[test]
+    fn ignore_cards_that_already_belong_to_the_player() {
+        let mut state = GameState::empty();
+        state.p2_hand = [Some(Card::basic_with(Arrows::ALL)), None, None, None, None];
+        state.board[0x5] = Cell::p2_card(Card::basic());
+
+        assert_eq!(most_threatening_card(&state, Player::P2), None);
+    }
+
+    #[test]
+    fn prefer_a_sure_flip_over_battles_that_are_likely_lost() {
+        let mut state = GameState::empty();
+        state.p2_hand = [
+            Some(Card::from_str("1P00", Arrows::LEFT | Arrows::RIGHT)),
+            Some(Card::from_str("1P00", Arrows::DOWN)),
+            None,
+            None,
+            None,
+        ];
+        state.board[0x4] = Cell::p1_card(Card::from_str("0PA0", Arrows::ALL));
+        state.board[0x6] = Cell::p1_card(Card::from_str("0PA0", Arrows::ALL));
+        state.board[0xF] = Cell::p1_card(Card::from_str("0PA0", Arrows::LEFT));
+
+        // card 0 reaches two cards from 5 but would most likely lose both battles
+        let threat = most_threatening_card(&state, Player::P2);
+        assert_eq!(threat, Some((1, 0xB, 1)));
+    }
+
+    #[test]
+    fn count_battles_that_are_likely_won() {
+        let mut state = GameState::empty();
+        state.p2_hand = [
+            Some(Card::from_str("BP00", Arrows::LEFT | Arrows::RIGHT)),
+            Some(Card::from_str("BP00", Arrows::DOWN)),
+            None,
+            None,
+            None,
+        ];
+        state.board[0x4] = Cell::p1_card(Card::from_str("0P00", Arrows::RIGHT));
+        state.board[0x6] = Cell::p1_card(Card::from_str("0P00", Arrows::LEFT));
+        state.board[0xF] = Cell::p1_card(Card::basic());
+
+        let threat = most_threatening_card(&state, Player::P2);
+        assert_eq!(threat, Some((0, 0x5, 2)));
+    }
+
+    #[test]
+    fn no_threat_when_the_hand_is_empty() {
+        let mut state = GameState::empty();
+        state.p2_hand = [None, None, None, None, None];
+
+        assert_eq!(most_threatening_card(&state, Player::P2), None);
+    }
+}
+
+#[cfg(test)]
+mod test_is_standoff {
+    use super::*;
+
+    #[test]
+    fn defenders_pointing_back_are_a_standoff() {
+        let defender = Card::basic_with(Arrows::LEFT);
+        assert!(is_standoff(defender, Arrows::RIGHT));
+
+        let defender = Card::basic_with(Arrows::DOWN_RIGHT | Arrows::UP);
+        assert!(is_standoff(defender, Arrows::UP_LEFT));
+    }
+
+    #[test]
+    fn defenders_pointing_elsewhere_are_not_a_standoff() {
+        let defender = Card::basic_with(Arrows::RIGHT);
+        assert!(!is_standoff(defender, Arrows::RIGHT));
+
+        let defender = Card::basic_with(Arrows::NONE);
+        assert!(!is_standoff(defender, Arrows::RIGHT));
+    }
+
+    #[test]
+    fn cards_on_the_board_pointing_at_each_other_have_a_standoff() {
+        let state = GameState::empty()
+            .cell(
+                0x5,
+                Cell::p1_card(Card::basic_with(Arrows::RIGHT | Arrows::DOWN)),
+            )
+            .cell(0x6, Cell::p2_card(Card::basic_with(Arrows::LEFT)))
+            .cell(0x9, Cell::p1_card(Card::basic_with(Arrows::UP)))
+            .cell(0xA, Cell::p1_card(Card::basic_with(Arrows::UP)));
+
+        assert!(has_standoff(&state.board, 0x5));
+        assert!(has_standoff(&state.board, 0x6));
+        // 9 points back at 5 but both belong to blue, and A points at 6 without it pointing back
+        assert!(!has_standoff(&state.board, 0x9));
+        assert!(!has_standoff(&state.board, 0xA));
+        assert!(!has_standoff(&state.board, 0x0));
+    }
+}
+
+#[cfg(test)]
+mod test_get_battle_digits {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn card(stats: &str) -> Card {
+        Card::from_str(stats, Arrows::NONE)
+    }
+
+    #[test]
+    fn match_the_stats_picked_in_a_battle() {
+        for (attacker, defender) in [
+            ("APBC", "APBC"),
+            ("AMBC", "APBC"),
+            ("AXBC", "APCB"),
+            ("AA1F", "CPBA"),
+        ] {
+            let (attacker, defender) = (card(attacker), card(defender));
+            let attack_stat = get_attack_stat(&rng(), attacker);
+            let defense_stat = get_defense_stat(&rng(), attacker, defender);
+
+            assert_eq!(
+                get_battle_digits(attacker, defender),
+                (attack_stat.digit, defense_stat.digit)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_get_tempo {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn count_each_reachable_opponent_card_once() {
+        let state = GameState::empty()
+            .p1_hand(&[
+                Card::basic_with(Arrows::UP),
+                Card::basic_with(Arrows::UP | Arrows::DOWN),
+            ])
+            .cell(0x1, Cell::p2_card(Card::basic()))
+            .cell(0x3, Cell::p2_card(Card::basic()))
+            .cell(0xC, Cell::p2_card(Card::basic()))
+            .cell(0xE, Cell::p1_card(Card::basic()))
+            .blocked(&[0x7]);
+
+        // 1 and C can be reached from 5 and 8, 3 can't be reached and E is the player's own
+        assert_eq!(get_tempo(&state, Player::P1), 2);
+    }
+
+    #[test]
+    fn only_count_battles_the_player_is_likely_to_win() {
+        let state = GameState::empty()
+            .p1_hand(&[Card::from_str("1P00", Arrows::UP)])
+            .cell(0x1, Cell::p2_card(Card::from_str("0PA0", Arrows::DOWN)));
+
+        // the battle is lopsided in favour of the defender
+        assert_eq!(get_tempo(&state, Player::P1), 0);
+
+        let state = state.p1_hand(&[Card::from_str("BP00", Arrows::UP)]);
+        assert_eq!(get_tempo(&state, Player::P1), 1);
+
+        // without the arrow pointing back the card is flipped outright
+        let state = state
+            .p1_hand(&[Card::from_str("1P00", Arrows::UP)])
+            .cell(0x1, Cell::p2_card(Card::from_str("0PA0", Arrows::NONE)));
+        assert_eq!(get_tempo(&state, Player::P1), 1);
+    }
+
+    #[test]
+    fn no_tempo_without_cards_in_hand() {
+        let state = GameState::empty()
+            .p1_hand(&[])
+            .cell(0x1, Cell::p2_card(Card::basic()));
+
+        assert_eq!(get_tempo(&state, Player::P1), 0);
+    }
+}
+
+#[cfg(test)]
+mod test_engaged_arrows {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn only_arrows_pointing_at_opponent_cards_are_engaged() {
+        let state = GameState::empty()
+            .cell(0x5, Cell::p1_card(Card::basic_with(Arrows::ALL)))
+            .cell(0x0, Cell::p2_card(Card::basic()))
+            .cell(0x6, Cell::p2_card(Card::basic()))
+            .cell(0x9, Cell::p1_card(Card::basic()))
+            .blocked(&[0x1]);
+
+        // 1 is blocked, 9 is the player's own and the other neighbours are empty
+        let engaged = engaged_arrows(&state.board, 0x5);
+        assert_eq!(engaged, Arrows::UP_LEFT | Arrows::RIGHT);
+    }
+
+    #[test]
+    fn arrows_pointing_off_the_board_in_a_corner_are_not_engaged() {
+        let state = GameState::empty()
+            .cell(0x0, Cell::p1_card(Card::basic_with(Arrows::ALL)))
+            .cell(0x1, Cell::p2_card(Card::basic()))
+            .cell(0x4, Cell::p2_card(Card::basic()))
+            .cell(0x5, Cell::p2_card(Card::basic()));
+
+        let engaged = engaged_arrows(&state.board, 0x0);
+        assert_eq!(engaged, Arrows::RIGHT | Arrows::DOWN_RIGHT | Arrows::DOWN);
+    }
+
+    #[test]
+    fn arrows_pointing_off_the_board_in_the_opposite_corner_are_not_engaged() {
+        let state = GameState::empty()
+            .cell(0xF, Cell::p2_card(Card::basic_with(Arrows::ALL)))
+            .cell(0xA, Cell::p1_card(Card::basic()))
+            .cell(0xB, Cell::p1_card(Card::basic()));
+
+        let engaged = engaged_arrows(&state.board, 0xF);
+        assert_eq!(engaged, Arrows::UP_LEFT | Arrows::UP);
+    }
+
+    #[test]
+    fn cells_without_a_card_have_no_engaged_arrows() {
+        let state = GameState::empty()
+            .cell(0x1, Cell::p2_card(Card::basic()))
+            .blocked(&[0x2]);
+
+        assert_eq!(engaged_arrows(&state.board, 0x0), Arrows::NONE);
+        assert_eq!(engaged_arrows(&state.board, 0x2), Arrows::NONE);
+    }
+}
+
+#[cfg(test)]
+mod test_get_flip_potential {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn best_placement_for_each_card() {
+        let state = GameState::empty()
+            .p1_hand(&[
+                Card::basic_with(Arrows::RIGHT),
+                Card::basic_with(Arrows::LEFT | Arrows::RIGHT),
+                Card::basic_with(Arrows::NONE),
+            ])
+            .cell(0x0, Cell::p2_card(Card::basic()))
+            .cell(0x2, Cell::p2_card(Card::basic()))
+            .cell(0x3, Cell::p1_card(Card::basic()));
+
+        // placed on 1 the second card points at both 0 and 2
+        let potential = get_flip_potential(&state, Player::P1);
+        assert_eq!(potential, [1, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn no_potential_without_empty_cells() {
+        let mut state = GameState::empty().p2_hand(&[Card::basic_with(Arrows::ALL)]);
+        state.board = [Cell::p1_card(Card::basic()); BOARD_SIZE];
+
+        assert_eq!(get_flip_potential(&state, Player::P2), [0; HAND_SIZE]);
+    }
+}
+
+#[cfg(test)]
+mod test_count_cards {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn empty_board() {
+        let state = GameState::empty();
+        assert_eq!(count_cards(&state.board), (0, 0));
+    }
+
+    #[test]
+    fn blocked_and_empty_cells_are_not_counted() {
+        let state = GameState::empty()
+            .cell(0x0, Cell::p1_card(Card::basic()))
+            .cell(0x5, Cell::p2_card(Card::basic()))
+            .cell(0x9, Cell::p2_card(Card::basic()))
+            .blocked(&[0x1, 0x2, 0xF]);
+
+        assert_eq!(count_cards(&state.board), (1, 2));
+    }
+
+    #[test]
+    fn count_mid_game_after_a_flip() {
+        let mut state = GameState::empty().cell(0x1, Cell::p2_card(Card::basic()));
+        let mut log = GameLog::new(state.turn);
+        state.p1_hand[0] = Some(Card::basic_with(Arrows::UP));
+
+        next(&mut state, &mut log, Input::place(0, 0x5)).unwrap();
+
+        assert_eq!(state.status, GameStatus::WaitingPlace);
+        assert_eq!(count_cards(&state.board), (2, 0));
+    }
+}
+
+#[cfg(test)]
+mod test_history {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn assert_same_state(state: &GameState, expected: &GameState) {
+        assert_eq!(state.status, expected.status);
+        assert_eq!(state.turn, expected.turn);
+        assert_eq!(state.turn_number, expected.turn_number);
+        assert_eq!(state.move_count, expected.move_count);
+        assert_eq!(state.board, expected.board);
+        assert_eq!(state.p1_hand, expected.p1_hand);
+        assert_eq!(state.p2_hand, expected.p2_hand);
+    }
+
+    #[test]
+    fn undo_a_placement() {
+        let mut state = GameState::empty().cell(0x1, Cell::p2_card(Card::basic()));
+        let mut log = GameLog::new(state.turn);
+        let mut history = History::default();
+        state.p1_hand[0] = Some(Card::basic_with(Arrows::UP));
+        let before = state.clone();
+
+        history
+            .next(&mut state, &mut log, Input::place(0, 0x5))
+            .unwrap();
+        history.undo(&mut state, &mut log).unwrap();
+
+        assert_same_state(&state, &before);
+        let log: Vec<_> = log.iter().collect();
+        assert_eq!(log, vec![&Entry::next_turn(Player::P1)]);
+    }
+
+    #[test]
+    fn undo_moves_one_at_a_time() {
+        let mut state = GameState::empty();
+        let mut log = GameLog::new(state.turn);
+        let mut history = History::default();
+        let first = state.clone();
+
+        history
+            .next(&mut state, &mut log, Input::place(0, 0x0))
+            .unwrap();
+        let second = state.clone();
+        history
+            .next(&mut state, &mut log, Input::place(0, 0x1))
+            .unwrap();
+
+        history.undo(&mut state, &mut log).unwrap();
+        assert_same_state(&state, &second);
+        assert_eq!(log.iter().count(), 3);
+
+        history.undo(&mut state, &mut log).unwrap();
+        assert_same_state(&state, &first);
+        assert_eq!(log.iter().count(), 1);
+    }
+
+    #[test]
+    fn forget_the_oldest_move_once_full() {
+        let mut state = GameState::empty();
+        let mut log = GameLog::new(state.turn);
+        let mut history = History::default();
+
+        for cell in 0..=History::CAPACITY {
+            history
+                .next(&mut state, &mut log, Input::place(cell / 2, cell))
+                .unwrap();
+        }
+        for _ in 0..History::CAPACITY {
+            history.undo(&mut state, &mut log).unwrap();
+        }
+
+        // only the first placement is left on the board
+        assert_eq!(state.move_count, 1);
+        assert_eq!(
+            history.undo(&mut state, &mut log),
+            Err("Nothing to undo".into())
+        );
+    }
+
+    #[test]
+    fn undoing_a_battle_doesnt_reroll_it() {
+        let mut state = GameState::empty();
+        let mut log = GameLog::new(state.turn);
+        let mut history = History::default();
+        state.p1_hand[0] = Some(Card::from_str("7P00", Arrows::UP | Arrows::DOWN));
+        state.board[0x1] = Cell::p2_card(Card::from_str("0P70", Arrows::DOWN));
+        state.board[0x9] = Cell::p2_card(Card::from_str("0P70", Arrows::UP));
+
+        history
+            .next(&mut state, &mut log, Input::place(0, 0x5))
+            .unwrap();
+        history
+            .next(&mut state, &mut log, Input::battle(0x1))
+            .unwrap();
+        let board = state.board;
+
+        history.undo(&mut state, &mut log).unwrap();
+        history
+            .next(&mut state, &mut log, Input::battle(0x1))
+            .unwrap();
+
+        assert_eq!(state.board, board);
+        assert_log_is_consistent(&log);
+    }
+
+    #[test]
+    fn undo_a_cancel() {
+        let mut state = GameState::empty();
+        let mut log = GameLog::new(state.turn);
+        let mut history = History::default();
+        let card = Card::from_str("7P00", Arrows::UP | Arrows::DOWN);
+        state.p1_hand[0] = Some(card);
+        state.board[0x1] = Cell::p2_card(Card::from_str("0P70", Arrows::DOWN));
+        state.board[0x9] = Cell::p2_card(Card::from_str("0P70", Arrows::UP));
+
+        history
+            .next(&mut state, &mut log, Input::place(0, 0x5))
+            .unwrap();
+        let placed = state.clone();
+        history.next(&mut state, &mut log, Input::Cancel).unwrap();
+        history.undo(&mut state, &mut log).unwrap();
+
+        assert_same_state(&state, &placed);
+        let log: Vec<_> = log.iter().collect();
+        assert_eq!(
+            log,
+            vec![
+                &Entry::next_turn(Player::P1),
+                &Entry::place_card(OwnedCard::p1(card), 0x5),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejected_inputs_are_not_remembered() {
+        let mut state = GameState::empty();
+        let mut log = GameLog::new(state.turn);
+        let mut history = History::default();
+
+        let res = history.next(&mut state, &mut log, Input::battle(0x0));
+        assert!(res.is_err());
+
+        let res = history.undo(&mut state, &mut log);
+        assert_eq!(res, Err("Nothing to undo".into()));
+    }
+}
+
+#[cfg(test)]
+mod test_get_battle_odds {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn card(attack: u8, physical_defense: u8) -> Card {
+        Card {
+            attack,
+            physical_defense,
+            ..Card::basic()
+        }
+    }
+
+    #[test]
+    fn zero_stats_always_draw() {
+        assert_eq!(get_battle_odds(card(0, 0), card(0, 0)), (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn count_every_pair_of_resolved_stats() {
+        // resolved stats: attack 0..=1, defense 0..=2
+        // attacker wins on (1, 0), draws on (0, 0) and (1, 1)
+        let (wins, losses, draws) = get_battle_odds(card(1, 0), card(0, 2));
+        assert_eq!(wins, 1.0 / 6.0);
+        assert_eq!(draws, 2.0 / 6.0);
+        assert!((losses - 3.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn odds_are_symmetric_for_equal_stats() {
+        let (wins, losses, _) = get_battle_odds(card(0xAF, 0), card(0, 0xAF));
+        assert!((wins - losses).abs() < 1e-9);
+    }
+
+    #[test]
+    fn odds_match_simulated_battles() {
+        let attacker = card(0x8F, 0);
+        let defender = card(0, 0x5F);
+        let (wins, _, _) = get_battle_odds(attacker, defender);
+
+        let rng = with_seed(0);
+        let battles = 100_000;
+        let simulated_wins = (0..battles)
+            .filter(|_| {
+                let result = calculate_battle_result(&rng, attacker, defender);
+                result.winner == BattleWinner::Attacker
+            })
+            .count();
+
+        assert!((wins - simulated_wins as f64 / battles as f64).abs() < 0.01);
+    }
+}
+
+#[cfg(test)]
+mod test_log_matches_board {
+    use super::*;
+
+    #[test]
+    fn the_last_owner_in_the_log_has_to_own_the_card() {
+        let card = Card::basic();
+        let state = GameState::empty()
+            .cell(0x0, Cell::p2_card(card))
+            .cell(0x1, Cell::p1_card(card));
+        let mut log = GameLog::new(Player::P1);
+        log.append(Entry::place_card(OwnedCard::p1(card), 0x0));
+        log.append(Entry::flip_card(
+            OwnedCard::p1(card),
+            0x0,
+            Player::P2,
+            FlipKind::Free,
+        ));
+
+        // 1 was never logged so it's skipped
+        assert!(log_matches_board(&log, &state.board));
+
+        log.append(Entry::flip_card(
+            OwnedCard::p2(card),
+            0x0,
+            Player::P1,
+            FlipKind::Combo,
+        ));
+        assert!(!log_matches_board(&log, &state.board));
+    }
+}
+
+#[cfg(test)]
+mod test_get_contested_cells {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn cells_are_contested_if_either_player_can_take_a_card_from_them() {
+        let state = GameState::empty()
+            .p1_hand(&[Card::basic_with(Arrows::LEFT)])
+            .p2_hand(&[Card::basic_with(Arrows::RIGHT)])
+            .cell(0x4, Cell::p2_card(Card::basic()))
+            .cell(0x6, Cell::p1_card(Card::basic()))
+            .cell(0x8, Cell::p2_card(Card::basic()));
+
+        let contested = get_contested_cells(&state);
+
+        // both players can reach an opponent card from 5
+        assert!(contested[0x5]);
+        // only blue can reach the card on 8 from 9
+        assert!(contested[0x9]);
+        // neither player can reach an opponent card from 1
+        assert!(!contested[0x1]);
+        // occupied cells are never contested
+        assert!(!contested[0x4]);
+        assert_eq!(contested.iter().filter(|&&c| c).count(), 2);
+    }
+}
+
+#[cfg(test)]
+mod test_random_games {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn owner(cell: &Cell) -> Option<Player> {
+        match cell {
+            Cell::Card(card) => Some(card.owner),
+            _ => None,
+        }
+    }
+
+    fn random_input(rng: &fastrand::Rng, state: &GameState) -> Input {
+        match &state.status {
+            GameStatus::WaitingPlace => {
+                let hand = match state.turn {
+                    Player::P1 => &state.p1_hand,
+                    Player::P2 => &state.p2_hand,
+                };
+                let cards: Vec<_> = (0..HAND_SIZE).filter(|&i| hand[i].is_some()).collect();
+                let cells: Vec<_> = (0..BOARD_SIZE)
+                    .filter(|&i| state.board[i] == Cell::Empty)
+                    .collect();
+                Input::place(
+                    cards[rng.usize(..cards.len())],
+                    cells[rng.usize(..cells.len())],
+                )
+            }
+            GameStatus::WaitingBattle { choices, .. } => {
+                Input::battle(choices[rng.usize(..choices.len())].0)
+            }
+            GameStatus::GameOver { .. } => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn last_score_matches_the_board_at_the_end_of_random_games() {
+        for seed in 0..100 {
+            let mut state = GameState::with_seed(seed, None, MAX_NUMBER_OF_BLOCKS);
+            let mut log = GameLog::new(state.turn);
+
+            while !matches!(state.status, GameStatus::GameOver { .. }) {
+                let cells: Vec<_> = (0..BOARD_SIZE)
+                    .filter(|&i| state.board[i] == Cell::Empty)
+                    .collect();
+                let input = match &state.status {
+                    GameStatus::WaitingBattle { choices, .. } => Input::battle(choices[0].0),
+                    _ => {
+                        let hand = match state.turn {
+                            Player::P1 => &state.p1_hand,
+                            Player::P2 => &state.p2_hand,
+                        };
+                        let card = hand.iter().position(Option::is_some).unwrap();
+                        Input::place(card, cells[0])
+                    }
+                };
+                next(&mut state, &mut log, input).unwrap();
+            }
+
+            let timeline = log.score_timeline();
+            // a full board can end the game before every card is placed
+            assert_eq!(timeline.len(), state.move_count as usize, "seed {seed}");
+            assert_eq!(
+                timeline.last(),
+                Some(&count_cards(&state.board)),
+                "seed {seed}"
+            );
+        }
+    }
+
+    #[test]
+    fn transcripts_of_random_games_round_trip() {
+        for seed in 0..200 {
+            let rng = with_seed(seed);
+            let mut state = GameState::with_seed(seed, None, MAX_NUMBER_OF_BLOCKS);
+            let mut log = GameLog::new(state.turn);
+
+            while !matches!(state.status, GameStatus::GameOver { .. }) {
+                let input = random_input(&rng, &state);
+                next(&mut state, &mut log, input).unwrap();
+            }
+
+            let parsed: GameLog = log.to_string().parse().unwrap();
+            let parsed: Vec<_> = parsed.iter().collect();
+            assert_eq!(parsed, log.iter().collect::<Vec<_>>(), "seed {seed}");
+        }
+    }
+
+    // plays a game with random inputs and checks the invariants after every move
+    fn play_random_game(seed: u64) {
+        let rng = with_seed(seed);
+        let mut state = GameState::with_seed(seed, None, MAX_NUMBER_OF_BLOCKS);
+        let mut log = GameLog::new(state.turn);
+
+        while !matches!(state.status, GameStatus::GameOver { .. }) {
+            let locked: Vec<_> = (0..BOARD_SIZE)
+                .filter(|&cell| is_locked(&state, cell))
+                .map(|cell| (cell, owner(&state.board[cell])))
+                .collect();
+            let remaining = get_remaining_moves(&state);
+
+            let input = random_input(&rng, &state);
+            next(&mut state, &mut log, input).unwrap();
+
+            for (cell, locked_owner) in locked {
+                let owner = owner(&state.board[cell]);
+                assert_eq!(
+                    owner, locked_owner,
+                    "seed {seed}: locked cell {cell:X} flipped"
+                );
+            }
+            assert!(get_remaining_moves(&state) <= remaining, "seed {seed}");
+        }
+
+        assert_log_is_consistent(&log);
+
+        let p1_cards = state.board.iter().filter(|c| owner(c) == Some(Player::P1));
+        let p2_cards = state.board.iter().filter(|c| owner(c) == Some(Player::P2));
+        let (p1_cards, p2_cards) = (p1_cards.count() as u8, p2_cards.count() as u8);
+        let placed = 2 * HAND_SIZE - get_remaining_moves(&state);
+        assert_eq!((p1_cards + p2_cards) as usize, placed, "seed {seed}");
+
+        let score = log
+            .iter()
+            .map(Entry::score_delta)
+            .fold((0, 0), |(p1, p2), (d1, d2)| (p1 + d1, p2 + d2));
+        assert_eq!(score, (p1_cards as i8, p2_cards as i8), "seed {seed}");
+
+        let winner = match p1_cards.cmp(&p2_cards) {
+            std::cmp::Ordering::Greater => Some(Player::P1),
+            std::cmp::Ordering::Less => Some(Player::P2),
+            std::cmp::Ordering::Equal => None,
+        };
+        assert_eq!(
+            state.status,
+            GameStatus::GameOver {
+                winner,
+                p1_cards,
+                p2_cards
+            },
+            "seed {seed}"
+        );
+    }
+
+    // plays each game separately so the failing seed is always reported, even when the
+    // panic comes from a helper that doesn't know the seed
+    fn play_random_games(seeds: std::ops::Range<u64>) {
+        for seed in seeds {
+            if std::panic::catch_unwind(|| play_random_game(seed)).is_err() {
+                panic!("random game failed with seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn play_random_games_to_the_end_without_breaking_any_invariants() {
+        play_random_games(0..200);
+    }
+
+    // too slow to run every time, run with `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn play_many_random_games_to_the_end_without_breaking_any_invariants() {
+        play_random_games(0..100_000);
+    }
+}
@@ -1,3 +1,5 @@
+mod args;
+mod events;
 mod game_log;
 mod input;
 mod logic;
@@ -5,9 +7,15 @@ mod render;
 
 const HAND_SIZE: usize = 5;
 const BOARD_SIZE: usize = 4 * 4;
-const MAX_NUMBER_OF_BLOCKS: u8 = 6;
+// the most cells that can be blocked while still leaving room for every card
+const DEFAULT_NUMBER_OF_BLOCKS: u8 = (BOARD_SIZE - 2 * HAND_SIZE) as u8;
+// blocking more than that can fill the board before every card is placed, which ends the game
+const MAX_NUMBER_OF_BLOCKS: u8 = 12;
+// the first and last placements of a game make up its opening and endgame, see GamePhase
+const OPENING_PLACEMENTS: usize = 3;
+const ENDGAME_PLACEMENTS: usize = 3;
 
-pub(crate) use game_log::{Entry, GameLog};
+pub(crate) use game_log::{Entry, FlipKind, GameLog};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum Player {
@@ -22,8 +30,32 @@ impl Player {
             Player::P2 => Player::P1,
         }
     }
+
+    // the lowercase name used in transcripts and events, read back by from_str
+    fn name(self) -> &'static str {
+        match self {
+            Player::P1 => "blue",
+            Player::P2 => "red",
+        }
+    }
+}
+
+// parses the player names used on screen, ignoring case
+impl std::str::FromStr for Player {
+    type Err = ParsePlayerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "blue" => Ok(Player::P1),
+            "red" => Ok(Player::P2),
+            _ => Err(ParsePlayerError),
+        }
+    }
 }
 
+#[derive(Debug, PartialEq)]
+struct ParsePlayerError;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum CardType {
     Physical,
@@ -32,6 +64,18 @@ enum CardType {
     Assault,
 }
 
+impl CardType {
+    // the letter used for the type when writing a card's stats, e.g. the P in 1P23
+    fn letter(self) -> char {
+        match self {
+            CardType::Physical => 'P',
+            CardType::Magical => 'M',
+            CardType::Exploit => 'X',
+            CardType::Assault => 'A',
+        }
+    }
+}
+
 // use a bitset to make the type smaller
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct Arrows(u8);
@@ -53,11 +97,58 @@ impl Arrows {
     const LEFT: Arrows = Arrows(0b0000_0010);
     const UP_LEFT: Arrows = Arrows(0b0000_0001);
 
+    // builds arrows from direction names, eg: ["up", "down_right", "l", "UL"]
+    fn from_directions(names: &[&str]) -> Result<Self, ArrowParseError> {
+        let directions = names
+            .iter()
+            .map(|name| match name.to_ascii_lowercase().as_str() {
+                "up" | "u" => Ok(Arrows::UP),
+                "up_right" | "ur" => Ok(Arrows::UP_RIGHT),
+                "right" | "r" => Ok(Arrows::RIGHT),
+                "down_right" | "dr" => Ok(Arrows::DOWN_RIGHT),
+                "down" | "d" => Ok(Arrows::DOWN),
+                "down_left" | "dl" => Ok(Arrows::DOWN_LEFT),
+                "left" | "l" => Ok(Arrows::LEFT),
+                "up_left" | "ul" => Ok(Arrows::UP_LEFT),
+                _ => {
+                    let direction = name.to_string();
+                    Err(ArrowParseError { direction })
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Arrows::combine(&directions))
+    }
+
+    // combines all of the given arrows into one
+    fn combine(directions: &[Arrows]) -> Self {
+        directions
+            .iter()
+            .fold(Arrows(0), |arrows, &direction| arrows | direction)
+    }
+
     // returns an Arrows with all of the arrows pointing in the opposite direction
     fn reverse(self) -> Self {
-        // wrapping shift by 4 bits
-        // this is effectively rotating the arrows by 180 degrees
-        Arrows(self.0 >> 4 | self.0 << 4)
+        self.rotate(4)
+    }
+
+    // rotates the arrows clockwise in steps of 45 degrees
+    fn rotate(self, steps: u8) -> Self {
+        // arrows go clockwise from the highest bit so a clockwise rotation is a wrapping shift to
+        // the right
+        Arrows(self.0.rotate_right(u32::from(steps % 8)))
+    }
+
+    // yields each set arrow on its own, clockwise from the top
+    fn iter(self) -> impl Iterator<Item = Arrows> {
+        (0..8)
+            .rev()
+            .map(|bit| Arrows(1 << bit))
+            .filter(move |&arrow| self.has(arrow))
+    }
+
+    // returns the number of set arrows
+    fn count(self) -> u32 {
+        self.0.count_ones()
     }
 
     fn has(self, other: Self) -> bool {
@@ -105,6 +196,21 @@ impl std::ops::BitOr for Arrows {
     }
 }
 
+#[derive(Debug, PartialEq)]
+struct ArrowParseError {
+    direction: String,
+}
+
+impl std::fmt::Display for ArrowParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let direction = &self.direction;
+        write!(
+            f,
+            "Invalid Direction {direction:?}, expected eg: up, up_right or ur"
+        )
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct Card {
     card_type: CardType,
@@ -130,9 +236,7 @@ impl Card {
                 n if n < 0.95 => randpick(rng, &[11, 12, 13]),    // 15%
                 _ => randpick(rng, &[14, 15]),                    // 5%
             };
-            // base stats range from 0x0 to 0xF
-            // real stats range from 0x0 to 0xFF
-            0x10 * base_stat + rng.u8(..16)
+            rng.u8(stat_range(base_stat))
         }
 
         let card_type = match rng.f32() {
@@ -152,6 +256,130 @@ impl Card {
             magical_defense: random_stat(rng),
         }
     }
+
+    // parses a card from its stats and arrows in hex, eg: "1P23_4"
+    // every stat is set to the highest real stat its digit can stand for
+    fn try_parse(value: &str) -> Result<Self, CardParseError> {
+        let (stats, arrows) = value.split_once('_').ok_or(CardParseError::Length)?;
+        let stats: Vec<char> = stats.chars().collect();
+        if stats.len() != 4 {
+            return Err(CardParseError::Length);
+        }
+
+        let stat = |pos: usize| match stats[pos].to_digit(16) {
+            Some(digit) => Ok(*stat_range(digit as u8).end()),
+            None => Err(CardParseError::Digit { pos }),
+        };
+
+        let card_type = match stats[1].to_ascii_uppercase() {
+            'P' => CardType::Physical,
+            'M' => CardType::Magical,
+            'X' => CardType::Exploit,
+            'A' => CardType::Assault,
+            found => return Err(CardParseError::CardType { found }),
+        };
+
+        let arrows = u8::from_str_radix(arrows, 16).map_err(|_| CardParseError::Arrows)?;
+
+        Ok(Card {
+            card_type,
+            attack: stat(0)?,
+            physical_defense: stat(2)?,
+            magical_defense: stat(3)?,
+            arrows: Arrows(arrows),
+        })
+    }
+}
+
+// formats the card the way Card::try_parse expects it, eg: "1P23_4"
+impl std::fmt::Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let att = stat_digit(self.attack);
+        let phy = stat_digit(self.physical_defense);
+        let mag = stat_digit(self.magical_defense);
+        let typ = self.card_type.letter();
+        let arrows = self.arrows.0;
+        write!(f, "{att:X}{typ}{phy:X}{mag:X}_{arrows:X}")
+    }
+}
+
+// names the part of the card that couldn't be parsed
+#[derive(Debug, PartialEq)]
+enum CardParseError {
+    // the stats aren't 4 characters long or aren't followed by an underscore and the arrows
+    Length,
+    Digit { pos: usize },
+    CardType { found: char },
+    Arrows,
+}
+
+impl std::fmt::Display for CardParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardParseError::Length => {
+                write!(
+                    f,
+                    "Expected 4 stats, an underscore and the arrows, eg: 1P23_4"
+                )
+            }
+            CardParseError::Digit { pos } => write!(f, "Invalid stat digit at position {pos}"),
+            CardParseError::CardType { found } => {
+                write!(f, "Invalid Card Type {found:?}, expected P, M, X or A")
+            }
+            CardParseError::Arrows => write!(f, "Invalid Arrows, expected a hex number up to FF"),
+        }
+    }
+}
+
+// reads a card in the format Card::try_parse expects, or with its arrows named after the stats
+// instead, eg: "1P23 up up_left"
+fn read_card(line: &str) -> Result<Card, String> {
+    let Some((stats, names)) = line.split_once(' ') else {
+        return Card::try_parse(line).map_err(|err| err.to_string());
+    };
+
+    let names: Vec<_> = names.split_whitespace().collect();
+    let arrows = Arrows::from_directions(&names).map_err(|err| err.to_string())?;
+    let card = Card::try_parse(&format!("{stats}_0")).map_err(|err| err.to_string())?;
+    Ok(Card { arrows, ..card })
+}
+
+// reads both hands from a list of cards, one per line with blue's hand first, skipping blank
+// lines
+fn read_hands(text: &str) -> Result<[[Option<Card>; HAND_SIZE]; 2], String> {
+    let mut cards = vec![];
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let card = read_card(line).map_err(|err| format!("Line {}: {err}", idx + 1))?;
+        cards.push(card);
+    }
+
+    if cards.len() != 2 * HAND_SIZE {
+        let found = cards.len();
+        return Err(format!("Expected {} cards, found {found}", 2 * HAND_SIZE));
+    }
+
+    let mut hands = [[None; HAND_SIZE]; 2];
+    for (slot, card) in hands.iter_mut().flatten().zip(cards) {
+        *slot = Some(card);
+    }
+    Ok(hands)
+}
+
+// stats are shown as a single hex digit (the base stat) but battles use the real stat
+// base stats range from 0x0 to 0xF
+// real stats range from 0x0 to 0xFF, each base stat covering 16 real stats
+
+fn stat_digit(real_stat: u8) -> u8 {
+    real_stat >> 4
+}
+
+fn stat_range(base_stat: u8) -> std::ops::RangeInclusive<u8> {
+    let low = 0x10 * base_stat;
+    low..=(low + 0xF)
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -160,19 +388,14 @@ struct OwnedCard {
     card: Card,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 enum Cell {
     Blocked,
     Card(OwnedCard),
+    #[default]
     Empty,
 }
 
-impl Default for Cell {
-    fn default() -> Self {
-        Cell::Empty
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum BattleWinner {
     Attacker,
@@ -206,27 +429,54 @@ enum GameStatus {
     WaitingBattle {
         attacker_cell: usize,
         choices: Vec<(usize, Card)>,
+        // the hand slot the attacker was placed from, only set until the first battle is
+        // fought so the placement can still be taken back
+        placed_from: Option<usize>,
     },
     GameOver {
         winner: Option<Player>,
+        // the final number of cards each player owns on the board, which decides the winner
+        p1_cards: u8,
+        p2_cards: u8,
     },
 }
 
+// rough stage of the game for the ui to adapt to, decided by the number of placements
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GamePhase {
+    Opening,
+    Midgame,
+    Endgame,
+}
+
 #[derive(Debug, Clone)]
 struct GameState {
+    // the seed the game was dealt from, along with the first turn and the max number of blocks
+    // it is enough to replay it
+    seed: u64,
+    max_blocks: u8,
     status: GameStatus,
     rng: fastrand::Rng,
     turn: Player,
+    // starts at 1 and counts up every time the turn changes
+    turn_number: u8,
+    // counts the cards placed so far, battle choices aren't counted
+    move_count: u8,
     board: [Cell; BOARD_SIZE],
     p1_hand: [Option<Card>; HAND_SIZE],
     p2_hand: [Option<Card>; HAND_SIZE],
 }
 
 impl GameState {
-    fn with_seed(seed: u64) -> Self {
+    // first_turn overrides the coin flip deciding who goes first
+    // note: the coin is flipped either way so that a seed always deals the same game
+    // max_blocks is expected to be at most MAX_NUMBER_OF_BLOCKS, anything over
+    // DEFAULT_NUMBER_OF_BLOCKS can leave too few cells for every card to be placed
+    fn with_seed(seed: u64, first_turn: Option<Player>, max_blocks: u8) -> Self {
         let status = GameStatus::WaitingPlace;
         let rng = fastrand::Rng::with_seed(seed);
-        let turn = if rng.bool() { Player::P1 } else { Player::P2 };
+        let coin_flip = if rng.bool() { Player::P1 } else { Player::P2 };
+        let turn = first_turn.unwrap_or(coin_flip);
         let mut board: [Cell; BOARD_SIZE] = Default::default();
         let p1_hand: [Option<Card>; HAND_SIZE] = [
             Some(Card::random(&rng)),
@@ -244,15 +494,19 @@ impl GameState {
         ];
 
         // block cells
-        for _ in 0..rng.u8(..=MAX_NUMBER_OF_BLOCKS) {
-            let idx = rng.usize(..(HAND_SIZE));
+        for _ in 0..rng.u8(..=max_blocks) {
+            let idx = rng.usize(..BOARD_SIZE);
             board[idx] = Cell::Blocked;
         }
 
         GameState {
+            seed,
+            max_blocks,
             status,
             rng,
             turn,
+            turn_number: 1,
+            move_count: 0,
             board,
             p1_hand,
             p2_hand,
@@ -273,6 +527,7 @@ impl GameState {
 enum Input {
     Place(InputPlace),
     Battle(InputBattle),
+    Cancel,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -287,24 +542,65 @@ struct InputBattle {
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut state = GameState::with_seed(fastrand::u64(..));
+    let args = args::parse(std::env::args().skip(1))?;
+
+    let seed = args.seed.unwrap_or_else(|| fastrand::u64(..));
+    let max_blocks = args.max_blocks.unwrap_or(DEFAULT_NUMBER_OF_BLOCKS);
+    let mut state = GameState::with_seed(seed, args.first_turn, max_blocks);
+    if let Some(path) = &args.hands {
+        let [p1_hand, p2_hand] = read_hands(&std::fs::read_to_string(path)?)
+            .map_err(|err| format!("Invalid hands in {path}: {err}"))?;
+        state.p1_hand = p1_hand;
+        state.p2_hand = p2_hand;
+    }
     let mut log = GameLog::new(state.turn);
+    let mut history = logic::History::default();
+
+    let mut events = match &args.events_json {
+        Some(path) => Some(std::fs::File::create(path)?),
+        None => None,
+    };
+    let mut logged = 0;
 
     let stdout = std::io::stdout();
     let mut out = stdout.lock();
     let stdin = std::io::stdin();
-    let mut in_ = stdin.lock();
+    let mut in_: Box<dyn std::io::BufRead> = match &args.replay {
+        Some(path) => Box::new(std::io::BufReader::new(std::fs::File::open(path)?)),
+        None => Box::new(stdin.lock()),
+    };
+    let mut line = 0;
 
     let mut buf = String::new();
     // game loop
     loop {
         use std::io::{BufRead, Write};
 
-        buf.clear();
-        render::clear(&mut buf);
-        render::screen(&log, &state, &mut buf)?;
-        out.write_all(buf.as_bytes())?;
-        out.flush()?;
+        if !args.headless {
+            buf.clear();
+            render::clear(&mut buf);
+            render::screen(&log, &state, &mut buf)?;
+            out.write_all(buf.as_bytes())?;
+            out.flush()?;
+        }
+
+        if events.is_some() || args.headless {
+            buf.clear();
+            logged = events::update(&mut buf, &log, logged, &state)?;
+            if let Some(events) = &mut events {
+                events.write_all(buf.as_bytes())?;
+                events.flush()?;
+            }
+            if args.headless {
+                out.write_all(buf.as_bytes())?;
+                out.flush()?;
+            }
+        }
+
+        // rewritten every time since an undo can take entries back out
+        if let Some(path) = &args.transcript {
+            std::fs::write(path, log.to_string())?;
+        }
 
         if let GameStatus::GameOver { .. } = state.status {
             break;
@@ -312,29 +608,482 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
         // input loop
         loop {
-            out.write_all(b"> ")?;
-            out.flush()?;
+            if !args.headless {
+                out.write_all(b"> ")?;
+                out.flush()?;
+            }
 
             // read and parse input
             buf.clear();
-            in_.read_line(&mut buf)?;
-            let input = match input::parse(&state, &buf) {
-                Err(input::Error::EmptyInput) => continue,
-                Err(err) => {
-                    println!("ERR: {}", err);
-                    continue;
+            if in_.read_line(&mut buf)? == 0 {
+                // nothing left to read, which a replay should never run into
+                if args.replay.is_some() {
+                    return Err("Replay ended before game over".into());
                 }
-                Ok(input) => input,
-            };
+                return Ok(());
+            }
+            line += 1;
 
-            if let Err(err) = logic::next(&mut state, &mut log, input) {
-                println!("ERR: {}", err);
+            // show the moves being replayed as if they were typed in
+            if args.replay.is_some() && !args.headless {
+                out.write_all(buf.as_bytes())?;
+            }
+
+            let res = if input::is_undo(&buf) {
+                history.undo(&mut state, &mut log)
             } else {
+                match input::parse(&state, &buf) {
+                    Err(input::Error::EmptyInput) => continue,
+                    Err(err) => Err(err.to_string()),
+                    Ok(input) => history.next(&mut state, &mut log, input),
+                }
+            };
+
+            match res {
                 // input was correctly evaluated, break input loop
-                break;
+                Ok(()) => break,
+                Err(err) if args.replay.is_some() => {
+                    return Err(format!("Replay failed on line {line}: {err}").into());
+                }
+                Err(err) if args.headless => {
+                    buf.clear();
+                    events::error(&mut buf, &err)?;
+                    out.write_all(buf.as_bytes())?;
+                    out.flush()?;
+                }
+                Err(err) => println!("ERR: {}", err),
             }
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test_stat_mapping {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn every_real_stat_maps_back_to_its_base_stat() {
+        for base_stat in 0..=0xF {
+            let range = stat_range(base_stat);
+            assert_eq!(range.clone().count(), 16);
+            for real_stat in range {
+                assert_eq!(stat_digit(real_stat), base_stat);
+            }
+        }
+    }
+
+    #[test]
+    fn base_stats_cover_all_real_stats() {
+        assert_eq!(stat_range(0x0), 0x00..=0x0F);
+        assert_eq!(stat_range(0xB), 0xB0..=0xBF);
+        assert_eq!(stat_range(0xF), 0xF0..=0xFF);
+    }
+}
+
+#[cfg(test)]
+mod test_player_from_str {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_player_names_ignoring_case() {
+        assert_eq!("Blue".parse(), Ok(Player::P1));
+        assert_eq!("red".parse(), Ok(Player::P2));
+        assert_eq!("RED".parse(), Ok(Player::P2));
+    }
+
+    #[test]
+    fn error_on_unknown_player() {
+        assert_eq!("green".parse::<Player>(), Err(ParsePlayerError));
+        assert_eq!(" blue".parse::<Player>(), Err(ParsePlayerError));
+        assert_eq!("p1".parse::<Player>(), Err(ParsePlayerError));
+    }
+}
+
+#[cfg(test)]
+mod test_arrows_rotate {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn rotate_clockwise() {
+        assert_eq!(Arrows::UP.rotate(1), Arrows::UP_RIGHT);
+        assert_eq!(Arrows::UP.rotate(2), Arrows::RIGHT);
+        assert_eq!(Arrows::RIGHT.rotate(3), Arrows::DOWN_LEFT);
+        assert_eq!(Arrows::UP_LEFT.rotate(1), Arrows::UP);
+    }
+
+    #[test]
+    fn rotate_full_turns_is_identity() {
+        let arrows = Arrows::UP | Arrows::DOWN_RIGHT | Arrows::LEFT;
+        assert_eq!(arrows.rotate(0), arrows);
+        assert_eq!(arrows.rotate(8), arrows);
+        assert_eq!(arrows.rotate(16), arrows);
+        assert_eq!(arrows.rotate(11), arrows.rotate(3));
+    }
+
+    #[test]
+    fn rotate_wraps_around() {
+        let arrows = Arrows::LEFT | Arrows::UP_LEFT;
+        assert_eq!(arrows.rotate(2), Arrows::UP | Arrows::UP_RIGHT);
+        assert_eq!(Arrows::ALL.rotate(5), Arrows::ALL);
+        assert_eq!(Arrows::NONE.rotate(5), Arrows::NONE);
+    }
+
+    #[test]
+    fn rotate_half_a_turn_is_reverse() {
+        for bits in 0..=u8::MAX {
+            let arrows = Arrows(bits);
+            assert_eq!(arrows.rotate(4), arrows.reverse());
+        }
+        assert_eq!(Arrows::UP_RIGHT.reverse(), Arrows::DOWN_LEFT);
+    }
+}
+
+#[cfg(test)]
+mod test_arrows_iter {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn no_arrows() {
+        assert_eq!(Arrows::NONE.iter().collect::<Vec<_>>(), vec![]);
+        assert_eq!(Arrows::NONE.count(), 0);
+    }
+
+    #[test]
+    fn all_arrows_clockwise_from_the_top() {
+        assert_eq!(
+            Arrows::ALL.iter().collect::<Vec<_>>(),
+            vec![
+                Arrows::UP,
+                Arrows::UP_RIGHT,
+                Arrows::RIGHT,
+                Arrows::DOWN_RIGHT,
+                Arrows::DOWN,
+                Arrows::DOWN_LEFT,
+                Arrows::LEFT,
+                Arrows::UP_LEFT,
+            ]
+        );
+        assert_eq!(Arrows::ALL.count(), 8);
+    }
+
+    #[test]
+    fn some_arrows() {
+        let arrows = Arrows(0b1001_0010);
+        assert_eq!(
+            arrows.iter().collect::<Vec<_>>(),
+            vec![Arrows::UP, Arrows::DOWN_RIGHT, Arrows::LEFT]
+        );
+        assert_eq!(arrows.count(), 3);
+
+        let arrows = Arrows::UP_LEFT;
+        assert_eq!(arrows.iter().collect::<Vec<_>>(), vec![Arrows::UP_LEFT]);
+        assert_eq!(arrows.count(), 1);
+    }
+}
+
+#[cfg(test)]
+mod test_card_display {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn display_stats_and_arrows() {
+        let card = Card {
+            card_type: CardType::Assault,
+            attack: 0x1F,
+            physical_defense: 0x2F,
+            magical_defense: 0xFF,
+            arrows: Arrows::UP | Arrows::UP_LEFT,
+        };
+        assert_eq!(card.to_string(), "1A2F_81");
+
+        let card = Card {
+            card_type: CardType::Exploit,
+            attack: 0x0F,
+            physical_defense: 0x0F,
+            magical_defense: 0x0F,
+            arrows: Arrows::NONE,
+        };
+        assert_eq!(card.to_string(), "0X00_0");
+    }
+
+    #[test]
+    fn display_round_trips_with_try_parse() {
+        for value in [
+            "0P00_0", "FPFF_FF", "1M23_4", "AXBC_10", "9X07_5A", "EA3D_0", "0A00_80", "7MF8_C3",
+        ] {
+            let card = Card::try_parse(value).unwrap();
+            assert_eq!(card.to_string(), value);
+            assert_eq!(Card::try_parse(&card.to_string()), Ok(card));
+        }
+    }
+
+    #[test]
+    fn display_real_stats_by_their_digit() {
+        let card = Card {
+            card_type: CardType::Magical,
+            attack: 0x10,
+            physical_defense: 0x2A,
+            magical_defense: 0x3F,
+            arrows: Arrows::DOWN,
+        };
+        assert_eq!(card.to_string(), "1M23_8");
+    }
+}
+
+#[cfg(test)]
+mod test_arrows_combine {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn combine_directions() {
+        let res = Arrows::combine(&[Arrows::UP, Arrows::RIGHT]);
+        assert_eq!(res, Arrows::UP | Arrows::RIGHT);
+    }
+
+    #[test]
+    fn combine_no_directions() {
+        assert_eq!(Arrows::combine(&[]), Arrows::NONE);
+    }
+
+    #[test]
+    fn combine_multiple_and_repeated_arrows() {
+        let res = Arrows::combine(&[Arrows::UP | Arrows::DOWN, Arrows::DOWN, Arrows::LEFT]);
+        assert_eq!(res, Arrows::UP | Arrows::DOWN | Arrows::LEFT);
+    }
+
+    #[test]
+    fn combine_iterated_arrows_back_together() {
+        let arrows = Arrows(0b1011_0100);
+        let directions: Vec<_> = arrows.iter().collect();
+        assert_eq!(Arrows::combine(&directions), arrows);
+    }
+}
+
+#[cfg(test)]
+mod test_card_try_parse {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_stats_and_arrows() {
+        let res = Card::try_parse("1A2F_81");
+        assert_eq!(
+            res,
+            Ok(Card {
+                card_type: CardType::Assault,
+                attack: 0x1F,
+                physical_defense: 0x2F,
+                magical_defense: 0xFF,
+                arrows: Arrows::UP | Arrows::UP_LEFT,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_lowercase_letters() {
+        let res = Card::try_parse("cxd0_ff");
+        assert_eq!(
+            res,
+            Ok(Card {
+                card_type: CardType::Exploit,
+                attack: 0xCF,
+                physical_defense: 0xDF,
+                magical_defense: 0x0F,
+                arrows: Arrows::ALL,
+            })
+        );
+    }
+
+    #[test]
+    fn error_on_bad_length() {
+        assert_eq!(Card::try_parse("1P23"), Err(CardParseError::Length));
+        assert_eq!(Card::try_parse("1P2_4"), Err(CardParseError::Length));
+        assert_eq!(Card::try_parse("1P234_4"), Err(CardParseError::Length));
+    }
+
+    #[test]
+    fn error_on_bad_digit() {
+        let res = Card::try_parse("GP23_4");
+        assert_eq!(res, Err(CardParseError::Digit { pos: 0 }));
+
+        let res = Card::try_parse("1P2z_4");
+        assert_eq!(res, Err(CardParseError::Digit { pos: 3 }));
+    }
+
+    #[test]
+    fn error_on_bad_card_type() {
+        let res = Card::try_parse("1Q23_4");
+        assert_eq!(res, Err(CardParseError::CardType { found: 'Q' }));
+    }
+
+    #[test]
+    fn error_on_bad_arrows() {
+        assert_eq!(Card::try_parse("1P23_"), Err(CardParseError::Arrows));
+        assert_eq!(Card::try_parse("1P23_G"), Err(CardParseError::Arrows));
+        assert_eq!(Card::try_parse("1P23_4 "), Err(CardParseError::Arrows));
+        assert_eq!(Card::try_parse("1P23_4_5"), Err(CardParseError::Arrows));
+        assert_eq!(Card::try_parse("1P23_100"), Err(CardParseError::Arrows));
+    }
+}
+
+#[cfg(test)]
+mod test_read_hands {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const HANDS: &str = "\
+0P00_0
+1P00_0
+2P00_0
+3P00_0
+4P00_0
+
+5M00_0
+6M00_0
+7M00_0
+8M00_0
+9M00_0
+";
+
+    #[test]
+    fn read_blue_then_red() {
+        let [p1_hand, p2_hand] = read_hands(HANDS).unwrap();
+        let digits =
+            |hand: [Option<Card>; HAND_SIZE]| hand.map(|card| stat_digit(card.unwrap().attack));
+        assert_eq!(digits(p1_hand), [0, 1, 2, 3, 4]);
+        assert_eq!(digits(p2_hand), [5, 6, 7, 8, 9]);
+        assert_eq!(p2_hand[0].unwrap().card_type, CardType::Magical);
+    }
+
+    #[test]
+    fn read_cards_with_named_arrows() {
+        let hands = HANDS.replace("0P00_0", "0P00 up UL");
+        let [p1_hand, _] = read_hands(&hands).unwrap();
+        let card = p1_hand[0].unwrap();
+        assert_eq!(card.arrows, Arrows::UP | Arrows::UP_LEFT);
+        assert_eq!(card.card_type, CardType::Physical);
+
+        let res = read_hands(&HANDS.replace("1P00_0", "1P00 up north"));
+        assert_eq!(
+            res,
+            Err("Line 2: Invalid Direction \"north\", expected eg: up, up_right or ur".into())
+        );
+    }
+
+    #[test]
+    fn error_on_the_wrong_number_of_cards() {
+        let res = read_hands("0P00_0\n1P00_0\n");
+        assert_eq!(res, Err("Expected 10 cards, found 2".into()));
+    }
+
+    #[test]
+    fn error_on_the_first_bad_card() {
+        let res = read_hands(&HANDS.replace("7M00_0", "7Q00_0"));
+        assert_eq!(
+            res,
+            Err("Line 9: Invalid Card Type 'Q', expected P, M, X or A".into())
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_arrows_from_directions {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_full_direction_names() {
+        let res = Arrows::from_directions(&["up", "up_right", "left"]);
+        assert_eq!(res, Ok(Arrows::UP | Arrows::UP_RIGHT | Arrows::LEFT));
+    }
+
+    #[test]
+    fn parse_abbreviations_ignoring_case() {
+        let res = Arrows::from_directions(&["UR", "dl", "D", "Up_Left"]);
+        assert_eq!(
+            res,
+            Ok(Arrows::UP_RIGHT | Arrows::DOWN_LEFT | Arrows::DOWN | Arrows::UP_LEFT)
+        );
+    }
+
+    #[test]
+    fn parse_no_names() {
+        assert_eq!(Arrows::from_directions(&[]), Ok(Arrows::NONE));
+    }
+
+    #[test]
+    fn error_on_unknown_direction() {
+        let res = Arrows::from_directions(&["up", "north"]);
+        assert_eq!(
+            res,
+            Err(ArrowParseError {
+                direction: "north".into()
+            })
+        );
+    }
+}
+
+#[cfg(test)]
+mod test_with_seed {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn fixing_the_first_turn_deals_the_same_game() {
+        let random = GameState::with_seed(42, None, DEFAULT_NUMBER_OF_BLOCKS);
+        let fixed =
+            GameState::with_seed(42, Some(random.turn.opposite()), DEFAULT_NUMBER_OF_BLOCKS);
+
+        assert_eq!(fixed.turn, random.turn.opposite());
+        assert_eq!(fixed.board, random.board);
+        assert_eq!(fixed.p1_hand, random.p1_hand);
+        assert_eq!(fixed.p2_hand, random.p2_hand);
+    }
+
+    #[test]
+    fn same_seed_deals_the_same_game() {
+        let first = GameState::with_seed(7, None, DEFAULT_NUMBER_OF_BLOCKS);
+        let second = GameState::with_seed(7, None, DEFAULT_NUMBER_OF_BLOCKS);
+        let other = GameState::with_seed(8, None, DEFAULT_NUMBER_OF_BLOCKS);
+
+        assert_eq!(first.turn, second.turn);
+        assert_eq!(first.board, second.board);
+        assert_eq!(first.p1_hand, second.p1_hand);
+        assert_eq!(first.p2_hand, second.p2_hand);
+        assert_ne!(first.p1_hand, other.p1_hand);
+    }
+
+    #[test]
+    fn no_blocks_when_max_blocks_is_zero() {
+        for seed in 0..100 {
+            let state = GameState::with_seed(seed, None, 0);
+            assert!(!state.board.contains(&Cell::Blocked), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn blocks_can_be_anywhere_on_the_board_up_to_max_blocks() {
+        let mut ever_blocked = [false; BOARD_SIZE];
+        for seed in 0..1000 {
+            let state = GameState::with_seed(seed, None, MAX_NUMBER_OF_BLOCKS);
+            let blocked = state.board.iter().filter(|&&cell| cell == Cell::Blocked);
+            assert!(
+                blocked.count() <= MAX_NUMBER_OF_BLOCKS as usize,
+                "seed {seed}"
+            );
+
+            for (cell, ever_blocked) in state.board.iter().zip(&mut ever_blocked) {
+                *ever_blocked |= *cell == Cell::Blocked;
+            }
+        }
+        assert_eq!(ever_blocked, [true; BOARD_SIZE]);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{
-    BattleWinner, Card, CardType, Cell, Entry, GameLog, GameState, GameStatus, OwnedCard, Player,
+    logic, stat_digit, Arrows, BattleWinner, Card, Cell, Entry, FlipKind, GameLog, GamePhase,
+    GameState, GameStatus, OwnedCard, Player, DEFAULT_NUMBER_OF_BLOCKS,
 };
 use std::fmt::Write;
 
@@ -19,25 +20,34 @@ pub(crate) fn clear(o: &mut String) {
 }
 
 pub(crate) fn screen(log: &GameLog, state: &GameState, o: &mut String) -> Result {
-    push_hand(o, Player::P1, &state.p1_hand)?;
+    push_hand(o, state, Player::P1, &state.p1_hand)?;
     writeln!(o)?;
 
     push_board(o, state)?;
     writeln!(o)?;
 
-    push_hand(o, Player::P2, &state.p2_hand)?;
+    push_hand(o, state, Player::P2, &state.p2_hand)?;
     writeln!(o)?;
 
     push_game_log(o, log)?;
+    push_score(o, log, state)?;
 
-    if let GameStatus::GameOver { winner } = state.status {
-        push_game_over(o, winner)
+    if let GameStatus::GameOver {
+        winner,
+        p1_cards,
+        p2_cards,
+    } = state.status
+    {
+        push_game_over(o, winner, p1_cards, p2_cards)?;
+        push_replay(o, log, state)
     } else {
         push_prompt(o, state)
     }
 }
 
-fn push_hand(o: &mut String, owner: Player, hand: &[Option<Card>; 5]) -> Result {
+fn push_hand(o: &mut String, state: &GameState, owner: Player, hand: &[Option<Card>; 5]) -> Result {
+    let potential = logic::get_flip_potential(state, owner);
+
     write!(o, "{}", owner.to_color())?;
 
     // line 1
@@ -89,9 +99,11 @@ fn push_hand(o: &mut String, owner: Player, hand: &[Option<Card>; 5]) -> Result
     }
     writeln!(o)?;
 
-    // line 5
-    for card in hand {
-        if card.is_some() {
+    // line 5, along with how many cards the card could flip at best
+    for (card, potential) in hand.iter().zip(potential) {
+        if card.is_some() && potential > 0 {
+            write!(o, "╚═══ +{potential} ══╝")?;
+        } else if card.is_some() {
             write!(o, "╚═════════╝")?;
         } else {
             write!(o, "           ")?;
@@ -101,6 +113,8 @@ fn push_hand(o: &mut String, owner: Player, hand: &[Option<Card>; 5]) -> Result
 }
 
 fn push_board(o: &mut String, state: &GameState) -> Result {
+    let contested = logic::get_contested_cells(state);
+
     writeln!(o, "   ┌───────────┬───────────┬───────────┬───────────┐")?;
 
     for (idx, &row) in [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11], [12, 13, 14, 15]]
@@ -111,11 +125,12 @@ fn push_board(o: &mut String, state: &GameState) -> Result {
         write!(o, "   │")?;
         for j in row {
             match &state.board[j] {
-                Cell::Card(OwnedCard { owner, card }) => {
-                    let ul = if card.arrows.up_left() { '⇖' } else { ' ' };
-                    let u = if card.arrows.up() { '⇑' } else { ' ' };
-                    let ur = if card.arrows.up_right() { '⇗' } else { ' ' };
-                    write!(o, "{} {ul}   {u}   {ur} {RESET}", owner.to_color())?;
+                Cell::Card(owned) => {
+                    let engaged = logic::engaged_arrows(&state.board, j);
+                    let ul = board_arrow(owned, engaged, Arrows::UP_LEFT, '⇖');
+                    let u = board_arrow(owned, engaged, Arrows::UP, '⇑');
+                    let ur = board_arrow(owned, engaged, Arrows::UP_RIGHT, '⇗');
+                    write!(o, "{} {ul}   {u}   {ur} {RESET}", owned.owner.to_color())?;
                 }
                 Cell::Blocked => {
                     write!(o, "{GRAY_BOLD} ╔═══════╗ {RESET}")?;
@@ -140,11 +155,12 @@ fn push_board(o: &mut String, state: &GameState) -> Result {
         // line 3 in row
         for j in row {
             match state.board[j] {
-                Cell::Card(OwnedCard { owner, card }) => {
-                    let l = if card.arrows.left() { '⇐' } else { ' ' };
-                    let r = if card.arrows.right() { '⇒' } else { ' ' };
-                    let stats = Stats::from(card);
-                    write!(o, "{} {l}  {stats} {r} {RESET}", owner.to_color())?;
+                Cell::Card(owned) => {
+                    let engaged = logic::engaged_arrows(&state.board, j);
+                    let l = board_arrow(&owned, engaged, Arrows::LEFT, '⇐');
+                    let r = board_arrow(&owned, engaged, Arrows::RIGHT, '⇒');
+                    let stats = Stats::from(owned.card);
+                    write!(o, "{} {l}  {stats} {r} {RESET}", owned.owner.to_color())?;
                 }
                 Cell::Blocked => {
                     write!(o, "{GRAY_BOLD} ║ BLOCK ║ {RESET}")?;
@@ -159,10 +175,16 @@ fn push_board(o: &mut String, state: &GameState) -> Result {
         // line 4 in row
         write!(o, "\n   │")?;
         for j in row {
-            if let Cell::Blocked = &state.board[j] {
-                write!(o, "{GRAY_BOLD} ║       ║ {RESET}")?;
-            } else {
-                write!(o, "           ")?;
+            match &state.board[j] {
+                Cell::Blocked => write!(o, "{GRAY_BOLD} ║       ║ {RESET}")?,
+                Cell::Card(_) if logic::is_locked(state, j) => {
+                    write!(o, "{GRAY}  secured  {RESET}")?;
+                }
+                Cell::Card(_) if logic::has_standoff(&state.board, j) => {
+                    write!(o, "{GRAY} standoff  {RESET}")?;
+                }
+                Cell::Empty if contested[j] => write!(o, "{GRAY} contested {RESET}")?,
+                _ => write!(o, "           ")?,
             }
             write!(o, "│")?;
         }
@@ -171,11 +193,12 @@ fn push_board(o: &mut String, state: &GameState) -> Result {
         // line 5 in row
         for j in row {
             match &state.board[j] {
-                Cell::Card(OwnedCard { owner, card }) => {
-                    let dl = if card.arrows.down_left() { '⇙' } else { ' ' };
-                    let d = if card.arrows.down() { '⇓' } else { ' ' };
-                    let dr = if card.arrows.down_right() { '⇘' } else { ' ' };
-                    write!(o, "{} {dl}   {d}   {dr} {RESET}", owner.to_color())?;
+                Cell::Card(owned) => {
+                    let engaged = logic::engaged_arrows(&state.board, j);
+                    let dl = board_arrow(owned, engaged, Arrows::DOWN_LEFT, '⇙');
+                    let d = board_arrow(owned, engaged, Arrows::DOWN, '⇓');
+                    let dr = board_arrow(owned, engaged, Arrows::DOWN_RIGHT, '⇘');
+                    write!(o, "{} {dl}   {d}   {dr} {RESET}", owned.owner.to_color())?;
                 }
                 Cell::Blocked => {
                     write!(o, "{GRAY_BOLD} ╚═══════╝ {RESET}")?;
@@ -193,6 +216,18 @@ fn push_board(o: &mut String, state: &GameState) -> Result {
     writeln!(o, "\n   └───────────┴───────────┴───────────┴───────────┘")
 }
 
+// arrows pointing at an opponent card are drawn in bold
+fn board_arrow(owned: &OwnedCard, engaged: Arrows, arrow: Arrows, glyph: char) -> String {
+    let color = owned.owner.to_color();
+    if engaged.has(arrow) {
+        format!("{}{glyph}{color}", owned.owner.to_color_bold())
+    } else if owned.card.arrows.has(arrow) {
+        glyph.to_string()
+    } else {
+        " ".into()
+    }
+}
+
 fn push_game_log(o: &mut String, log: &GameLog) -> Result {
     writeln!(o, "                   {GRAY_BOLD} ══ GAMELOG ══ {RESET}")?;
 
@@ -228,9 +263,13 @@ fn push_game_log(o: &mut String, log: &GameLog) -> Result {
                 card,
                 cell,
                 to,
-                via_combo,
+                kind,
             } => {
-                let prefix = if *via_combo { "Combo'd " } else { "Flipped " };
+                let prefix = match kind {
+                    FlipKind::Free => "Flipped ",
+                    FlipKind::Battle => "Beaten  ",
+                    FlipKind::Combo => "Combo'd ",
+                };
                 let stats = Stats::from(card);
                 write!(o, "{prefix}{stats} on cell {cell:X} to {to}")?;
             }
@@ -287,24 +326,85 @@ fn push_game_log(o: &mut String, log: &GameLog) -> Result {
     Ok(())
 }
 
+fn push_score(o: &mut String, log: &GameLog, state: &GameState) -> Result {
+    let timeline = log.score_timeline();
+    let (p1_cards, p2_cards) = timeline[timeline.len() - 1];
+    write!(o, "     Score │ ")?;
+    write!(o, "{} {p1_cards} - {p2_cards} {} ", Player::P1, Player::P2)?;
+
+    let p1_tempo = logic::get_tempo(state, Player::P1);
+    let p2_tempo = logic::get_tempo(state, Player::P2);
+    writeln!(
+        o,
+        "{GRAY}( cards under threat: {p2_tempo} - {p1_tempo} ){RESET}"
+    )
+}
+
 fn push_prompt(o: &mut String, state: &GameState) -> Result {
     let prefix = match state.turn {
         Player::P1 => "",
         Player::P2 => " ",
     };
+    let turn_number = state.turn_number;
+    let move_count = state.move_count;
+    let remaining = logic::get_remaining_moves(state);
+    let phase = logic::get_phase(state);
+    write!(o, "{GRAY}      Turn │ {turn_number}, {phase} ")?;
+    writeln!(
+        o,
+        "( {move_count} placements made, {remaining} left ){RESET}"
+    )?;
     write!(o, "{prefix}Next: {} │ ", state.turn)?;
 
     match &state.status {
         GameStatus::WaitingPlace => {
             write!(o, "Where to place which card? ")?;
-            writeln!(o, "{GRAY}( format: {{CARD#}} {{COORD}} ){RESET}")?;
+            if move_count > 0 {
+                writeln!(
+                    o,
+                    "{GRAY}( format: {{CARD#}} {{COORD}}, or U to undo ){RESET}"
+                )?;
+            } else {
+                writeln!(o, "{GRAY}( format: {{CARD#}} {{COORD}} ){RESET}")?;
+            }
+
+            let opponent = state.turn.opposite();
+            if let Some((card, cell, count)) = logic::most_threatening_card(state, opponent) {
+                write!(o, "  Watch out for {opponent} card {card:X} ")?;
+                writeln!(
+                    o,
+                    "{GRAY}( can reach {count} card(s) from cell {cell:X} ){RESET}"
+                )?;
+            }
         }
-        GameStatus::WaitingBattle { choices, .. } => {
+        GameStatus::WaitingBattle {
+            attacker_cell,
+            choices,
+            placed_from,
+        } => {
             write!(o, "Which card to battle? ")?;
-            writeln!(o, "{GRAY}( format: {{COORD}} ){RESET}")?;
+            if placed_from.is_some() {
+                writeln!(
+                    o,
+                    "{GRAY}( format: {{COORD}}, or X to take back the card ){RESET}"
+                )?;
+            } else {
+                writeln!(o, "{GRAY}( format: {{COORD}}, or U to undo ){RESET}")?;
+            }
+            let attacker = match state.board[*attacker_cell] {
+                Cell::Card(OwnedCard { card, .. }) => card,
+                _ => unreachable!(),
+            };
             for &(cell, card) in choices {
-                let stats = Stats::from(card).owner(state.turn.opposite());
-                writeln!(o, "  {cell:X} ( {stats} )")?;
+                let (att_digit, def_digit) = logic::get_battle_digits(attacker, card);
+                let att_stats = Stats::from(attacker).owner(state.turn).highlight(att_digit);
+                let def_stats = Stats::from(card)
+                    .owner(state.turn.opposite())
+                    .highlight(def_digit);
+                let (wins, _, _) = logic::get_battle_odds(attacker, card);
+                let wins = wins * 100.0;
+                write!(o, "  {cell:X} ( {att_stats} vs {def_stats} ) ")?;
+                writeln!(o, "{GRAY}{wins:.0}% to win{RESET}")?;
             }
         }
         GameStatus::GameOver { .. } => unreachable!(),
@@ -313,20 +413,40 @@ fn push_prompt(o: &mut String, state: &GameState) -> Result {
     Ok(())
 }
 
-fn push_game_over(o: &mut String, winner: Option<Player>) -> Result {
+fn push_game_over(o: &mut String, winner: Option<Player>, p1_cards: u8, p2_cards: u8) -> Result {
     write!(o, " {WHITE_BOLD}Game Over{RESET} │ ")?;
     match winner {
         Some(winner) => {
-            writeln!(o, "{winner} Wins")?;
+            let (winner_cards, loser_cards) = match winner {
+                Player::P1 => (p1_cards, p2_cards),
+                Player::P2 => (p2_cards, p1_cards),
+            };
+            write!(o, "{winner} Wins ")?;
+            writeln!(o, "{GRAY}( {winner_cards} cards to {loser_cards} ){RESET}")?;
         }
         None => {
-            writeln!(o, "It was a draw!")?;
+            write!(o, "It was a draw! ")?;
+            writeln!(o, "{GRAY}( {p1_cards} cards each ){RESET}")?;
         }
     }
 
     Ok(())
 }
 
+fn push_replay(o: &mut String, log: &GameLog, state: &GameState) -> Result {
+    let first = match log.iter().next() {
+        Some(Entry::NextTurn { turn: Player::P1 }) => "blue",
+        Some(Entry::NextTurn { turn: Player::P2 }) => "red",
+        _ => unreachable!("game log always starts with the first turn"),
+    };
+    let seed = state.seed;
+    write!(o, "{GRAY}    Replay │ --seed {seed} --first {first}")?;
+    if state.max_blocks != DEFAULT_NUMBER_OF_BLOCKS {
+        write!(o, " --max-blocks {}", state.max_blocks)?;
+    }
+    writeln!(o, "{RESET}")
+}
+
 impl Player {
     fn to_color(self) -> &'static str {
         match self {
@@ -353,6 +473,17 @@ impl std::fmt::Display for Player {
     }
 }
 
+impl std::fmt::Display for GamePhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            GamePhase::Opening => "opening",
+            GamePhase::Midgame => "midgame",
+            GamePhase::Endgame => "endgame",
+        };
+        write!(f, "{name}")
+    }
+}
+
 struct Stats {
     card: Card,
     owner: Option<Player>,
@@ -405,15 +536,10 @@ impl From<&OwnedCard> for Stats {
 
 impl std::fmt::Display for Stats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let att = self.card.attack >> 4;
-        let phy = self.card.physical_defense >> 4;
-        let mag = self.card.magical_defense >> 4;
-        let typ = match self.card.card_type {
-            CardType::Physical => 'P',
-            CardType::Magical => 'M',
-            CardType::Exploit => 'X',
-            CardType::Assault => 'A',
-        };
+        let att = stat_digit(self.card.attack);
+        let phy = stat_digit(self.card.physical_defense);
+        let mag = stat_digit(self.card.magical_defense);
+        let typ = self.card.card_type.letter();
 
         if let Some(owner) = self.owner {
             let color = owner.to_color();
@@ -0,0 +1,90 @@
+use std::process::{Command, Output};
+
+fn path(file: &str) -> String {
+    format!("{}/tests/replays/{file}", env!("CARGO_MANIFEST_DIR"))
+}
+
+fn replay_with(file: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tetra-master"))
+        .args(["--seed", "3", "--first", "blue", "--replay", &path(file)])
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn replay(file: &str) -> Output {
+    replay_with(file, &[])
+}
+
+#[test]
+fn replay_a_full_game() {
+    let output = replay("seed_3_first_blue.txt");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Game Over"));
+    assert!(stdout.contains("( 8 cards to 2 )"));
+    assert!(stdout.contains("--seed 3 --first blue"));
+}
+
+#[test]
+fn stop_on_the_first_invalid_move() {
+    let output = replay("seed_3_first_blue_invalid.txt");
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Replay failed on line 2: Cell 0 is not empty"));
+}
+
+#[test]
+fn stop_when_the_replay_ends_before_game_over() {
+    let output = replay("seed_3_first_blue_truncated.txt");
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Replay ended before game over"));
+}
+
+#[test]
+fn replay_a_full_game_headless() {
+    let output = replay_with("seed_3_first_blue.txt", &["--headless"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let expected = std::fs::read_to_string(path("seed_3_first_blue.jsonl")).unwrap();
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn write_a_transcript_of_the_game() {
+    let transcript = std::env::temp_dir().join("tetra-master-replay-transcript.txt");
+    let output = replay_with(
+        "seed_3_first_blue.txt",
+        &["--headless", "--transcript", transcript.to_str().unwrap()],
+    );
+    assert!(output.status.success());
+
+    let transcript = std::fs::read_to_string(transcript).unwrap();
+    let lines: Vec<_> = transcript.lines().collect();
+    assert_eq!(lines.first(), Some(&"next_turn blue"));
+    assert_eq!(
+        lines.iter().filter(|l| l.starts_with("place_card")).count(),
+        10
+    );
+}
+
+#[test]
+fn play_with_hands_from_a_file() {
+    let output = replay_with(
+        "seed_3_first_blue.txt",
+        &["--headless", "--hands", &path("hands_without_arrows.txt")],
+    );
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let setup = stdout.lines().next().unwrap();
+    assert!(setup.contains(r#""p1_hand":[{"owner":"blue","stats":"0P00","arrows":[]}"#));
+    assert!(setup.contains(r#"{"owner":"red","stats":"9MAB","arrows":[]}]"#));
+    // without arrows no card is ever flipped
+    assert!(stdout.contains(r#""p1_cards":5,"p2_cards":5"#));
+}